@@ -1,13 +1,13 @@
-use crate::configuration::ModelCustomization;
 use crate::data::action::Action;
+use crate::data::context_entry::ContextEntry;
 use crate::data::extra_data::ExtraData;
 use crate::data::roles::GameRole;
 use crate::game::Game;
-use crate::llm::ai_interface::AIInterface;
-use crate::load_message_scene;
-use async_openai::types::chat::ChatCompletionTools;
-use tokio::sync::mpsc::channel;
+use crate::llm::ai_interface::{AIInterface, LlmError};
+use async_openai::types::chat::{ChatCompletionTools, FunctionCall};
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BaseActor {
     pub name: String,
     pub id: u8,
@@ -18,62 +18,59 @@ pub struct BaseActor {
 }
 
 impl BaseActor {
-    pub async fn prompt(&self, prompt: &str, game: &Game, tools: &[ChatCompletionTools]) -> Action {
+    pub async fn prompt(
+        &self,
+        prompt: &str,
+        game: &Game,
+        tools: &[ChatCompletionTools],
+    ) -> Result<PromptReply, LlmError> {
         match &self.kind {
-            ActorKind::Real => real_prompt(prompt, game).await,
-            ActorKind::Llm(llm) => llm.send_request_with_tools(prompt, tools).await,
+            ActorKind::Real => Ok(PromptReply {
+                action: game.frontend().prompt_player(self.id, prompt).await,
+                raw: None,
+                tool_calls: Vec::new(),
+            }),
+            ActorKind::Llm(llm) => llm.send_request_with_tools(prompt, game, tools).await,
+            ActorKind::Scripted => Ok(crate::scripted::decide(self, game, tools)),
         }
     }
 }
 
+pub struct PromptReply {
+    pub action: Action,
+    /// The raw reasoning entry to record, LLMs only
+    pub raw: Option<ContextEntry>,
+    /// The tool calls exactly as the model sent them, LLMs only
+    pub tool_calls: Vec<FunctionCall>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ActorKind {
     Real,
     Llm(AIInterface),
+    /// Follows a fixed strategy instead of asking a model, see [`crate::scripted`]
+    Scripted,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ModelCustomization {
+    pub sprite_path: String,
+    /// RGBA, kept engine-agnostic so the game can run without Godot
+    pub color: [f32; 4],
 }
 
-pub async fn real_prompt(prompt: &str, game: &Game) -> Action {
-    let another_prompt = prompt.to_string();
-    let prompt = prompt.to_string();
-    let (sender, mut receiver) = channel::<Option<Action>>(1);
-    game.command_sender
-        .send(crate::chat::ChatCommand::Closure(Box::new(move |chat| {
-            let sender = sender.clone();
-            let message = load_message_scene().instantiate_as::<godot::classes::Control>();
-            message
-                .get_node_as::<godot::classes::Label>("Container/Background/Sayer")
-                .set_text("System");
-            message
-                .get_node_as::<godot::classes::RichTextLabel>("Container/Content")
-                .set_text(&prompt);
-            chat.get_message_list().add_child(&message);
-            let send = chat.get_send_button();
-            let mut input = chat.get_input_box();
-            godot::task::spawn(async move {
-                send.signals().pressed().to_future().await;
-                let text = input.get_text();
-                input.clear();
-                let mut results = Vec::new();
-                for line in text.to_string().lines() {
-                    if let Some(action) = parse_real_command(line) {
-                        results.push(action);
-                    }
-                }
-                if results.is_empty() {
-                    sender.send(None).await.unwrap();
-                    return;
-                }
-                if results.len() == 1 {
-                    sender.send(Some(results[0].clone())).await.unwrap();
-                } else {
-                    sender.send(Some(Action::MultiCall(results))).await.unwrap();
-                }
-            });
-        })))
-        .unwrap();
-    let result = receiver.recv().await.unwrap();
-    match result {
-        None => Box::pin(real_prompt(&another_prompt, game)).await,
-        Some(action) => action,
+/// Parses the playable actor's input, one command per line
+pub fn parse_real_input(text: &str) -> Option<Action> {
+    let mut results = Vec::new();
+    for line in text.lines() {
+        if let Some(action) = parse_real_command(line) {
+            results.push(action);
+        }
+    }
+    match results.len() {
+        0 => None,
+        1 => results.pop(),
+        _ => Some(Action::MultiCall(results)),
     }
 }
 
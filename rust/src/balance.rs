@@ -0,0 +1,251 @@
+use crate::data::roles::{GameRole, RoleAlignment};
+use crate::game::setup::{GameSetup, RoleSlot};
+use crate::simulation::{
+    ActorConfig, BatchGame, BatchOptions, SimulationConfig, default_api_base, run_batch,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The space of setups to search and who plays them
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BalanceConfig {
+    #[serde(default = "default_api_base")]
+    pub api_base: String,
+    /// Falls back to the `API_KEY` environment variable
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Seats take turns between these models. Scripted actors play every seat when empty.
+    #[serde(default)]
+    pub models: Vec<String>,
+    pub player_counts: Vec<usize>,
+    /// Extra messages per discussion to try with every role mix
+    #[serde(default = "default_extra_messages")]
+    pub extra_messages: Vec<u8>,
+    /// Every candidate is played with the same seeds, counting up from this one
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub start_at_night: bool,
+}
+
+fn default_extra_messages() -> Vec<u8> {
+    vec![GameSetup::default().extra_messages]
+}
+
+/// A setup to try at a player count
+#[derive(Clone)]
+pub struct Candidate {
+    pub players: usize,
+    pub setup: GameSetup,
+}
+
+impl BalanceConfig {
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&text).map_err(std::io::Error::other)?;
+        if config.player_counts.is_empty() || config.extra_messages.is_empty() {
+            return Err(std::io::Error::other(
+                "At least one player count and message budget are needed",
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Every Mafia count that doesn't start at parity, with and without a Doctor and a Sheriff,
+    /// for every player count and message budget
+    pub fn candidates(&self) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+        for &players in &self.player_counts {
+            for mafia in (1..).take_while(|mafia| mafia * 2 < players) {
+                for (doctor, sheriff) in
+                    [(false, false), (true, false), (false, true), (true, true)]
+                {
+                    let mut roles = vec![RoleSlot::Fixed(GameRole::MAFIOSO); mafia];
+                    if doctor {
+                        roles.push(RoleSlot::Fixed(GameRole::DOCTOR));
+                    }
+                    if sheriff {
+                        roles.push(RoleSlot::Fixed(GameRole::SHERIFF));
+                    }
+                    if roles.len() > players {
+                        continue;
+                    }
+                    for &extra_messages in &self.extra_messages {
+                        let mut name = format!("{} players: {} Mafia", players, mafia);
+                        if doctor {
+                            name.push_str(", Doctor");
+                        }
+                        if sheriff {
+                            name.push_str(", Sheriff");
+                        }
+                        name.push_str(&format!(", {} extra messages", extra_messages));
+                        candidates.push(Candidate {
+                            players,
+                            setup: GameSetup {
+                                name,
+                                roles: roles.clone(),
+                                mafia_scaling: None,
+                                extra_messages,
+                                neutral_killers_suspicious: false,
+                            },
+                        });
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    pub fn lobby(&self, candidate: &Candidate) -> SimulationConfig {
+        let actors = (0..candidate.players)
+            .map(|seat| ActorConfig {
+                name: format!("Player {}", seat + 1),
+                model_id: self
+                    .models
+                    .get(seat % self.models.len().max(1))
+                    .cloned()
+                    .unwrap_or_default(),
+                scripted: self.models.is_empty(),
+                role: None,
+                instructions: None,
+                reasoning_effort: None,
+            })
+            .collect();
+        SimulationConfig {
+            api_base: self.api_base.clone(),
+            api_key: self.api_key.clone(),
+            actors,
+            seed: self.seed,
+            start_at_night: self.start_at_night,
+            prompts: Default::default(),
+            setup: Some(candidate.setup.clone()),
+            belief_probes: Vec::new(),
+            roles: Vec::new(),
+        }
+    }
+}
+
+/// How one candidate did over its games
+#[derive(Clone, Serialize)]
+pub struct SetupResult {
+    pub players: usize,
+    pub setup: GameSetup,
+    pub town_wins: u32,
+    pub mafia_wins: u32,
+    /// Stopped early or failed, these don't count toward the win rate
+    pub unfinished: u32,
+}
+
+impl SetupResult {
+    pub fn finished(&self) -> u32 {
+        self.town_wins + self.mafia_wins
+    }
+
+    pub fn town_win_rate(&self) -> f64 {
+        self.town_wins as f64 / self.finished().max(1) as f64
+    }
+
+    /// How far the Town win rate is from 50%. Setups without a finished game are as far as it gets.
+    pub fn imbalance(&self) -> f64 {
+        if self.finished() == 0 {
+            return 0.5;
+        }
+        (self.town_win_rate() - 0.5).abs()
+    }
+}
+
+/// Plays `games` games of every candidate, all in one batch so the parallelism spans candidates
+pub async fn evaluate(
+    config: &BalanceConfig,
+    candidates: &[Candidate],
+    games: usize,
+    options: BatchOptions,
+) -> Vec<SetupResult> {
+    let lobbies = candidates
+        .iter()
+        .flat_map(|candidate| {
+            let lobby = config.lobby(candidate);
+            (0..games).map(move |game| (lobby.clone(), config.seed.wrapping_add(game as u64)))
+        })
+        .collect();
+    let results = run_batch(lobbies, options).await;
+    candidates
+        .iter()
+        .zip(results.chunks(games.max(1)))
+        .map(|(candidate, games)| tally(candidate, games))
+        .collect()
+}
+
+fn tally(candidate: &Candidate, games: &[BatchGame]) -> SetupResult {
+    let mut result = SetupResult {
+        players: candidate.players,
+        setup: candidate.setup.clone(),
+        town_wins: 0,
+        mafia_wins: 0,
+        unfinished: 0,
+    };
+    for game in games {
+        match (&game.summary.result, &game.summary.error) {
+            (Some(end), None) => match end.faction {
+                Some(RoleAlignment::Town) => result.town_wins += 1,
+                Some(RoleAlignment::Mafia) => result.mafia_wins += 1,
+                Some(RoleAlignment::Neutral) | None => result.unfinished += 1,
+            },
+            _ => result.unfinished += 1,
+        }
+    }
+    result
+}
+
+/// The setup closest to an even game at every player count, named so the configuration screen can list them
+pub fn recommend(results: &[SetupResult]) -> Vec<GameSetup> {
+    let mut player_counts = results
+        .iter()
+        .map(|result| result.players)
+        .collect::<Vec<_>>();
+    player_counts.sort();
+    player_counts.dedup();
+    player_counts
+        .into_iter()
+        .filter_map(|players| {
+            let best = results
+                .iter()
+                .filter(|result| result.players == players && result.finished() > 0)
+                .min_by(|a, b| a.imbalance().total_cmp(&b.imbalance()))?;
+            Some(GameSetup {
+                name: format!("Balanced ({} players)", players),
+                ..best.setup.clone()
+            })
+        })
+        .collect()
+}
+
+/// A Markdown table per player count, most balanced first
+pub fn render_results(results: &[SetupResult]) -> String {
+    let mut sorted = results.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| {
+        a.players
+            .cmp(&b.players)
+            .then(a.imbalance().total_cmp(&b.imbalance()))
+    });
+    let mut builder = String::new();
+    let mut current_players = None;
+    for result in sorted {
+        if current_players != Some(result.players) {
+            current_players = Some(result.players);
+            builder.push_str(&format!(
+                "\n### {} players\n\n| Setup | Town Win % | Town | Mafia | Unfinished |\n| --- | --- | --- | --- | --- |\n",
+                result.players
+            ));
+        }
+        builder.push_str(&format!(
+            "| {} | {:.1} | {} | {} | {} |\n",
+            result.setup.name,
+            100.0 * result.town_win_rate(),
+            result.town_wins,
+            result.mafia_wins,
+            result.unfinished
+        ));
+    }
+    builder
+}
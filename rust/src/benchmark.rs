@@ -0,0 +1,430 @@
+use crate::actor::{ActorKind, BaseActor, ModelCustomization};
+use crate::data::action::Action;
+use crate::data::channel::Channel;
+use crate::data::context_entry::{ContextEntry, SayerType};
+use crate::data::event::GameEvent;
+use crate::data::extra_data::ExtraData;
+use crate::data::roles::GameRole;
+use crate::frontend::headless::NullFrontend;
+use crate::game::Game;
+use crate::llm::ai_interface::AIInterface;
+use crate::llm::tools::{discussion_tools, pick_tools, voting_tools};
+use crate::prompts::general::{time_to_vote, your_turn_to_talk};
+use crate::prompts::specific::doctor::pick_to_protect;
+use crate::prompts::specific::sheriff::pick_to_investigate;
+use crate::simulation::panic_message;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+/// A hand-written mid-game situation with a single decision to score
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// IDs are the positions in this list
+    pub actors: Vec<ScenarioActor>,
+    /// Follows the usual introduction every game starts with
+    pub context: Vec<ScenarioEntry>,
+    /// Who has to decide
+    pub decider: u8,
+    pub decision: Decision,
+    /// The decision scores the points of the best outcome it matches, or nothing
+    pub outcomes: Vec<Outcome>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScenarioActor {
+    pub name: String,
+    pub role: GameRole,
+    #[serde(default)]
+    pub dead: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScenarioEntry {
+    /// A system message when unset
+    #[serde(default)]
+    pub speaker: Option<u8>,
+    #[serde(default = "global_channel")]
+    pub channel: Channel,
+    pub text: String,
+}
+
+fn global_channel() -> Channel {
+    Channel::Global
+}
+
+/// The decision points of a game, each asked with the prompt and tools the game uses
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Decision {
+    Discuss,
+    Vote,
+    Investigate,
+    Protect,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Outcome {
+    #[serde(flatten)]
+    pub expect: Expectation,
+    pub points: f64,
+}
+
+/// What a decision has to contain. Parts of a `MultiCall` are checked one by one.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "expect")]
+pub enum Expectation {
+    /// Provided this ID, as a vote or a night pick
+    Picked {
+        target: u8,
+    },
+    Abstained,
+    /// Said or whispered something containing any of these words, ignoring case
+    Mentioned {
+        words: Vec<String>,
+    },
+    Whispered {
+        to: u8,
+    },
+    Tagged {
+        target: u8,
+    },
+    AllOf {
+        of: Vec<Expectation>,
+    },
+}
+
+impl Expectation {
+    pub fn matches(&self, action: &Action) -> bool {
+        if let Expectation::AllOf { of } = self {
+            return of.iter().all(|expectation| expectation.matches(action));
+        }
+        if let Action::MultiCall(actions) = action {
+            return actions.iter().any(|action| self.matches(action));
+        }
+        match (self, action) {
+            (Expectation::Picked { target }, Action::ProvideID(id)) => target == id,
+            (Expectation::Abstained, Action::Abstain) => true,
+            (Expectation::Mentioned { words }, Action::Talk(message))
+            | (Expectation::Mentioned { words }, Action::Whisper(_, message)) => {
+                let message = message.to_lowercase();
+                words
+                    .iter()
+                    .any(|word| message.contains(&word.to_lowercase()))
+            }
+            (Expectation::Whispered { to }, Action::Whisper(id, _)) => to == id,
+            (Expectation::Tagged { target }, Action::TagPlayerForComment(id)) => target == id,
+            _ => false,
+        }
+    }
+}
+
+impl Scenario {
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let scenario: Self = serde_json::from_str(&text).map_err(std::io::Error::other)?;
+        if scenario.decider as usize >= scenario.actors.len() {
+            return Err(std::io::Error::other(format!(
+                "{} has no actor with the deciding ID {}",
+                scenario.name, scenario.decider
+            )));
+        }
+        Ok(scenario)
+    }
+
+    /// Every `.json` file in `dir`, sorted by file name
+    pub fn read_all(dir: &Path) -> std::io::Result<Vec<Self>> {
+        let mut paths = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.retain(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        });
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| {
+                Self::read(path)
+                    .map_err(|err| std::io::Error::other(format!("{}: {}", path.display(), err)))
+            })
+            .collect()
+    }
+
+    pub fn best_score(&self) -> f64 {
+        self.outcomes
+            .iter()
+            .map(|outcome| outcome.points)
+            .fold(0.0, f64::max)
+    }
+
+    pub fn score(&self, action: &Action) -> f64 {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.expect.matches(action))
+            .map(|outcome| outcome.points)
+            .reduce(f64::max)
+            .unwrap_or(0.0)
+    }
+
+    /// A game holding the scenario, with every actor played by `model_id`
+    pub fn build_game(&self, model_id: &str, seed: u64) -> Game {
+        let mut game = Game::new(Box::new(NullFrontend), None, seed);
+        game.state.actors = self
+            .actors
+            .iter()
+            .enumerate()
+            .map(|(index, actor)| BaseActor {
+                name: actor.name.clone(),
+                id: index as u8,
+                role: actor.role.clone(),
+                extra_data: if actor.dead {
+                    vec![ExtraData::Dead]
+                } else {
+                    Vec::new()
+                },
+                kind: ActorKind::Llm(AIInterface {
+                    model_id: model_id.to_string(),
+                    owner_id: index as u8,
+                    instructions: None,
+                    reasoning_effort: None,
+                }),
+                model_customization: ModelCustomization {
+                    sprite_path: String::new(),
+                    color: [1.0, 1.0, 1.0, 1.0],
+                },
+            })
+            .collect();
+        game.init_context(false);
+        for entry in &self.context {
+            game.add_to_context(ContextEntry {
+                content: entry.text.clone(),
+                sayer_type: entry.speaker.map_or(SayerType::System, SayerType::Actor),
+                extra_data: vec![ExtraData::SaidInChannel(entry.channel.clone())],
+            });
+        }
+        game
+    }
+
+    /// Asks the decider of `game` the scenario's question
+    pub async fn decide(&self, game: &mut Game) -> Action {
+        let role = game
+            .state
+            .get_actor_from_id(self.decider)
+            .unwrap()
+            .role
+            .name();
+        let (prompt, tools) = match self.decision {
+            Decision::Discuss => {
+                let actor = game.state.get_actor_from_id(self.decider).unwrap();
+                let prompt = your_turn_to_talk(
+                    &game.prompts,
+                    actor,
+                    game.state.get_nondead_ids().len() as u8,
+                    game.setup.extra_messages,
+                );
+                (prompt, discussion_tools())
+            }
+            Decision::Vote => (time_to_vote(&game.prompts), voting_tools()),
+            Decision::Investigate => (pick_to_investigate(&game.prompts, &role), pick_tools()),
+            Decision::Protect => (pick_to_protect(&game.prompts, &role), pick_tools()),
+        };
+        game.prompt_actor(self.decider, &prompt, &tools).await
+    }
+}
+
+/// One model's answer to one scenario
+#[derive(Clone, Serialize)]
+pub struct ScenarioRun {
+    pub scenario: String,
+    pub model_id: String,
+    pub run: usize,
+    pub score: f64,
+    pub best_score: f64,
+    /// The model answered without calling a tool
+    pub no_tool_call: bool,
+    pub action: Option<String>,
+    /// What the request panicked with, if it did
+    pub error: Option<String>,
+}
+
+impl ScenarioRun {
+    pub async fn play(scenario: &Scenario, model_id: &str, run: usize) -> Self {
+        let mut game = scenario.build_game(model_id, run as u64);
+        let action = scenario.decide(&mut game).await;
+        if let Some(failure) = &game.aborted {
+            return Self {
+                scenario: scenario.name.clone(),
+                model_id: model_id.to_string(),
+                run,
+                score: 0.0,
+                best_score: scenario.best_score(),
+                no_tool_call: false,
+                action: None,
+                error: Some(failure.to_string()),
+            };
+        }
+        let no_tool_call = game.state.events.iter().any(
+            |event| matches!(event, GameEvent::Decided { tool_calls, .. } if tool_calls.is_empty()),
+        );
+        Self {
+            scenario: scenario.name.clone(),
+            model_id: model_id.to_string(),
+            run,
+            score: scenario.score(&action),
+            best_score: scenario.best_score(),
+            no_tool_call,
+            action: Some(describe(&action)),
+            error: None,
+        }
+    }
+}
+
+fn describe(action: &Action) -> String {
+    match action {
+        Action::Abstain => String::from("Abstain"),
+        Action::Whisper(to, message) => format!("Whisper to {}: {}", to, message),
+        Action::TagPlayerForComment(id) => format!("Tag {}", id),
+        Action::ProvideID(id) => format!("Pick {}", id),
+        Action::Talk(message) => format!("Talk: {}", message),
+        Action::MultiCall(actions) => actions.iter().map(describe).collect::<Vec<_>>().join(" | "),
+    }
+}
+
+/// Plays every scenario `runs` times with every model, `parallel` requests at a time.
+/// A request that panics is recorded as a failed run.
+pub async fn run_benchmark(
+    scenarios: Vec<Scenario>,
+    models: Vec<String>,
+    runs: usize,
+    parallel: usize,
+) -> Scorecard {
+    let scenarios = Arc::new(scenarios);
+    let jobs = models
+        .iter()
+        .flat_map(|model| {
+            (0..scenarios.len())
+                .flat_map(move |scenario| (0..runs).map(move |run| (model.clone(), scenario, run)))
+        })
+        .collect::<Vec<_>>();
+    let mut finished = vec![None; jobs.len()];
+    let mut task_jobs = HashMap::new();
+    let mut pending = JoinSet::new();
+    let mut next_job = 0;
+    loop {
+        while pending.len() < parallel.max(1) && next_job < jobs.len() {
+            let (model_id, scenario, run) = jobs[next_job].clone();
+            let scenarios = Arc::clone(&scenarios);
+            let task = pending.spawn(async move {
+                ScenarioRun::play(&scenarios[scenario], &model_id, run).await
+            });
+            task_jobs.insert(task.id(), next_job);
+            next_job += 1;
+        }
+        match pending.join_next_with_id().await {
+            Some(Ok((task, run))) => finished[task_jobs[&task]] = Some(run),
+            Some(Err(err)) => {
+                let index = task_jobs[&err.id()];
+                let (model_id, scenario, run) = &jobs[index];
+                let error = if err.is_panic() {
+                    panic_message(err.into_panic())
+                } else {
+                    err.to_string()
+                };
+                finished[index] = Some(ScenarioRun {
+                    scenario: scenarios[*scenario].name.clone(),
+                    model_id: model_id.clone(),
+                    run: *run,
+                    score: 0.0,
+                    best_score: scenarios[*scenario].best_score(),
+                    no_tool_call: false,
+                    action: None,
+                    error: Some(error),
+                });
+            }
+            None => break,
+        }
+    }
+    let runs = finished.into_iter().flatten().collect();
+    Scorecard {
+        scenarios: scenarios
+            .iter()
+            .map(|scenario| scenario.name.clone())
+            .collect(),
+        models,
+        runs,
+    }
+}
+
+/// Every run of a benchmark, in the order the scenarios and models were given
+#[derive(Default, Serialize)]
+pub struct Scorecard {
+    pub scenarios: Vec<String>,
+    pub models: Vec<String>,
+    pub runs: Vec<ScenarioRun>,
+}
+
+impl Scorecard {
+    /// The share of the best score `model_id` got on `scenario`, over every run. `None` for no runs.
+    pub fn percentage(&self, model_id: &str, scenario: Option<&str>) -> Option<f64> {
+        let runs = self
+            .runs
+            .iter()
+            .filter(|run| {
+                run.model_id == model_id && scenario.is_none_or(|name| run.scenario == name)
+            })
+            .collect::<Vec<_>>();
+        if runs.is_empty() {
+            return None;
+        }
+        let score = runs.iter().map(|run| run.score).sum::<f64>();
+        let best = runs.iter().map(|run| run.best_score).sum::<f64>();
+        Some(100.0 * score / best.max(f64::EPSILON))
+    }
+
+    /// A Markdown table of scenarios by models, with a total and the runs without a tool call
+    pub fn render(&self) -> String {
+        let mut builder = String::from("| Scenario |");
+        for model in &self.models {
+            builder.push_str(&format!(" {} |", model));
+        }
+        builder.push_str("\n| --- |");
+        builder.push_str(&" --- |".repeat(self.models.len()));
+        builder.push('\n');
+        let cell =
+            |value: Option<f64>| value.map_or(String::from("-"), |value| format!("{:.0}%", value));
+        for scenario in &self.scenarios {
+            builder.push_str(&format!("| {} |", scenario));
+            for model in &self.models {
+                builder.push_str(&format!(
+                    " {} |",
+                    cell(self.percentage(model, Some(scenario)))
+                ));
+            }
+            builder.push('\n');
+        }
+        builder.push_str("| **Total** |");
+        for model in &self.models {
+            builder.push_str(&format!(" **{}** |", cell(self.percentage(model, None))));
+        }
+        builder.push_str("\n| No tool call / failed |");
+        for model in &self.models {
+            let (missing, failed) = self.runs.iter().filter(|run| run.model_id == *model).fold(
+                (0, 0),
+                |(missing, failed), run| {
+                    (
+                        missing + run.no_tool_call as u32,
+                        failed + run.error.is_some() as u32,
+                    )
+                },
+            );
+            builder.push_str(&format!(" {} / {} |", missing, failed));
+        }
+        builder.push('\n');
+        builder
+    }
+}
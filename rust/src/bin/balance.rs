@@ -0,0 +1,128 @@
+use rust::balance::{BalanceConfig, evaluate, recommend, render_results};
+use rust::game::setup::write_presets;
+use rust::llm::ai_interface::set_client;
+use rust::logging::init_headless;
+use rust::simulation::BatchOptions;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: balance <balance.json> [options]
+
+Options:
+    --games <n>         Games per candidate setup (default 20)
+    --parallel <n>      Games to run at the same time (default 1)
+    --out <dir>         Where the report and presets go (default balance)
+    --max-phases <n>    Stop a game after this many days and nights (default 40)
+
+Logs go to <out>/logs. LOG_LEVEL and FILE_LOG_LEVEL set how much is logged to the
+console and to the file, for example LOG_LEVEL=info or FILE_LOG_LEVEL=trace.";
+
+struct Options {
+    config: PathBuf,
+    games: usize,
+    parallel: usize,
+    out: PathBuf,
+    max_phases: u32,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        config: PathBuf::new(),
+        games: 20,
+        parallel: 1,
+        out: PathBuf::from("balance"),
+        max_phases: 40,
+    };
+    let mut config = None;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--games" => options.games = parse_number(&value("--games")?)?,
+            "--parallel" => options.parallel = parse_number(&value("--parallel")?)?,
+            "--out" => options.out = PathBuf::from(value("--out")?),
+            "--max-phases" => options.max_phases = parse_number(&value("--max-phases")?)?,
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => config = Some(PathBuf::from(arg)),
+        }
+    }
+    if options.games == 0 {
+        return Err(String::from("--games must be at least 1"));
+    }
+    options.config = config.ok_or_else(|| String::from("No balance file given"))?;
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("{} is not a valid number", text))
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("{}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    init_headless(&options.out.join("logs"));
+    let config = match BalanceConfig::read(&options.config) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", options.config.display(), err);
+            std::process::exit(1);
+        }
+    };
+    let candidates = config.candidates();
+    if !config.models.is_empty() {
+        set_client(&config.api_base, &config.lobby(&candidates[0]).api_key());
+    }
+    println!(
+        "Playing {} games of {} candidate setups",
+        options.games,
+        candidates.len()
+    );
+    let batch_options = BatchOptions {
+        parallel: options.parallel,
+        max_phases: Some(options.max_phases),
+        fail_fast: false,
+        out: None,
+        reasoning: false,
+        events: false,
+        quiet: true,
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let results = runtime.block_on(evaluate(&config, &candidates, options.games, batch_options));
+
+    let report = render_results(&results);
+    let presets = recommend(&results);
+    let report_path = options.out.join("report.md");
+    if let Err(err) =
+        std::fs::create_dir_all(&options.out).and_then(|_| std::fs::write(&report_path, &report))
+    {
+        eprintln!("Failed to write {}: {}", report_path.display(), err);
+    }
+    let presets_path = options.out.join("presets.json");
+    if let Err(err) = write_presets(&presets, &presets_path) {
+        eprintln!("Failed to write {}: {}", presets_path.display(), err);
+    }
+    print!("{}", report);
+    println!("\nRecommended:");
+    for preset in &presets {
+        let roles = preset
+            .roles
+            .iter()
+            .map(|role| role.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "    {}: {}, {} extra messages",
+            preset.name, roles, preset.extra_messages
+        );
+    }
+    println!("Presets written to {}", presets_path.display());
+}
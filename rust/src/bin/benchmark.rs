@@ -0,0 +1,122 @@
+use rust::benchmark::{Scenario, run_benchmark};
+use rust::llm::ai_interface::set_client;
+use rust::logging::init_headless;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: benchmark --model <id> [--model <id>...] [options]
+
+Options:
+    --model <id>        A model to score, can be given several times
+    --scenarios <dir>   Where the scenario files are (default scenarios)
+    --runs <n>          Times every model answers every scenario (default 3)
+    --parallel <n>      Requests to send at the same time (default 4)
+    --out <dir>         Where the scorecard goes (default benchmark)
+    --api-base <url>    The API to use (default https://openrouter.ai/api/v1)
+
+The API key is read from the API_KEY environment variable.
+
+Logs go to <out>/logs. LOG_LEVEL and FILE_LOG_LEVEL set how much is logged to the
+console and to the file, for example LOG_LEVEL=info or FILE_LOG_LEVEL=trace.";
+
+struct Options {
+    models: Vec<String>,
+    scenarios: PathBuf,
+    runs: usize,
+    parallel: usize,
+    out: PathBuf,
+    api_base: String,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        models: Vec::new(),
+        scenarios: PathBuf::from("scenarios"),
+        runs: 3,
+        parallel: 4,
+        out: PathBuf::from("benchmark"),
+        api_base: String::from("https://openrouter.ai/api/v1"),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--model" => options.models.push(value("--model")?),
+            "--scenarios" => options.scenarios = PathBuf::from(value("--scenarios")?),
+            "--runs" => options.runs = parse_number(&value("--runs")?)?,
+            "--parallel" => options.parallel = parse_number(&value("--parallel")?)?,
+            "--out" => options.out = PathBuf::from(value("--out")?),
+            "--api-base" => options.api_base = value("--api-base")?,
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    if options.models.is_empty() {
+        return Err(String::from("No model given"));
+    }
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("{} is not a valid number", text))
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("{}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    init_headless(&options.out.join("logs"));
+    let scenarios = match Scenario::read_all(&options.scenarios) {
+        Ok(scenarios) if !scenarios.is_empty() => scenarios,
+        Ok(_) => {
+            eprintln!("No scenarios in {}", options.scenarios.display());
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("Could not read the scenarios: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let api_key = std::env::var("API_KEY").unwrap_or_default();
+    set_client(&options.api_base, api_key.trim());
+    println!(
+        "Scoring {} models on {} scenarios, {} runs each",
+        options.models.len(),
+        scenarios.len(),
+        options.runs
+    );
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let scorecard = runtime.block_on(run_benchmark(
+        scenarios,
+        options.models,
+        options.runs,
+        options.parallel,
+    ));
+
+    let table = scorecard.render();
+    let markdown_path = options.out.join("scorecard.md");
+    let json_path = options.out.join("scorecard.json");
+    if let Err(err) = std::fs::create_dir_all(&options.out)
+        .and_then(|_| std::fs::write(&markdown_path, &table))
+        .and_then(|_| {
+            std::fs::write(
+                &json_path,
+                serde_json::to_string_pretty(&scorecard).unwrap(),
+            )
+        })
+    {
+        eprintln!(
+            "Failed to write the scorecard to {}: {}",
+            options.out.display(),
+            err
+        );
+    }
+    print!("{}", table);
+}
@@ -0,0 +1,203 @@
+use rust::data::roles::{GameRole, RoleAlignment, load_roles};
+use rust::export::dataset::{DatasetFilter, write_dataset};
+use rust::llm::ai_interface::set_client;
+use rust::logging::init_headless;
+use rust::simulation::{BatchOptions, BatchSummary, SimulationConfig, run_batch};
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: simulate <config.json> [options]
+
+Options:
+    --games <n>         Number of games to play (default 1)
+    --parallel <n>      Games to run at the same time (default 1)
+    --out <dir>         Where transcripts, saves and the summary go (default simulations)
+    --seed <n>          Overrides the seed of the config
+    --max-phases <n>    Stop a game after this many days and nights
+    --fail-fast         Stop starting new games once one has failed
+    --reasoning         Include every model's reasoning in the transcripts
+    --events            Stream every game's events to <out>/events as JSON lines while it runs
+    --dataset <file>    Also write the winning side's decisions as a fine-tuning dataset
+    --dataset-all       Keep the losing side's decisions in the dataset too
+    --dataset-role <role>
+                        Only keep decisions of players with this role
+    --dataset-alignment <Town|Mafia|Neutral>
+                        Only keep decisions of players with this alignment
+    --dataset-model <id>
+                        Only keep decisions of this model
+    --roles <dir>       Load extra role definitions from the JSON files in this directory
+
+Logs go to <out>/logs. LOG_LEVEL and FILE_LOG_LEVEL set how much is logged to the
+console and to the file, for example LOG_LEVEL=info or FILE_LOG_LEVEL=trace.";
+
+struct Options {
+    config: PathBuf,
+    games: usize,
+    parallel: usize,
+    out: PathBuf,
+    seed: Option<u64>,
+    max_phases: Option<u32>,
+    fail_fast: bool,
+    reasoning: bool,
+    events: bool,
+    dataset: Option<PathBuf>,
+    dataset_filter: DatasetFilter,
+    roles: Option<PathBuf>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        config: PathBuf::new(),
+        games: 1,
+        parallel: 1,
+        out: PathBuf::from("simulations"),
+        seed: None,
+        max_phases: None,
+        fail_fast: false,
+        reasoning: false,
+        events: false,
+        dataset: None,
+        dataset_filter: DatasetFilter {
+            winners_only: true,
+            ..Default::default()
+        },
+        roles: None,
+    };
+    let mut config = None;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--games" => options.games = parse_number(&value("--games")?)?,
+            "--parallel" => options.parallel = parse_number::<usize>(&value("--parallel")?)?.max(1),
+            "--out" => options.out = PathBuf::from(value("--out")?),
+            "--seed" => options.seed = Some(parse_number(&value("--seed")?)?),
+            "--max-phases" => options.max_phases = Some(parse_number(&value("--max-phases")?)?),
+            "--fail-fast" => options.fail_fast = true,
+            "--reasoning" => options.reasoning = true,
+            "--events" => options.events = true,
+            "--dataset" => options.dataset = Some(PathBuf::from(value("--dataset")?)),
+            "--dataset-all" => options.dataset_filter.winners_only = false,
+            "--dataset-role" => {
+                options.dataset_filter.role = Some(GameRole(Cow::Owned(value("--dataset-role")?)))
+            }
+            "--dataset-alignment" => {
+                options.dataset_filter.alignment =
+                    Some(parse_alignment(&value("--dataset-alignment")?)?)
+            }
+            "--dataset-model" => options.dataset_filter.model_id = Some(value("--dataset-model")?),
+            "--roles" => options.roles = Some(PathBuf::from(value("--roles")?)),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => config = Some(PathBuf::from(arg)),
+        }
+    }
+    options.config = config.ok_or_else(|| String::from("No config file given"))?;
+    Ok(options)
+}
+
+fn parse_alignment(text: &str) -> Result<RoleAlignment, String> {
+    match text {
+        "Town" => Ok(RoleAlignment::Town),
+        "Mafia" => Ok(RoleAlignment::Mafia),
+        "Neutral" => Ok(RoleAlignment::Neutral),
+        _ => Err(format!("{} is not an alignment", text)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("{} is not a valid number", text))
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("{}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    init_headless(&options.out.join("logs"));
+    let mut config = match SimulationConfig::read(&options.config) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", options.config.display(), err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(directory) = &options.roles {
+        match load_roles(directory) {
+            Ok(definitions) => {
+                let names = definitions
+                    .iter()
+                    .map(|definition| definition.name.clone())
+                    .collect::<Vec<_>>();
+                println!("Loaded the roles {}", names.join(", "));
+                config.roles.extend(definitions);
+            }
+            Err(err) => {
+                eprintln!(
+                    "Could not load the roles in {}: {}",
+                    directory.display(),
+                    err
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Err(err) = config.validate() {
+        eprintln!("{} can't be played: {}", options.config.display(), err);
+        std::process::exit(1);
+    }
+    if let Some(role) = &options.dataset_filter.role
+        && !config.role_book().contains(role)
+    {
+        eprintln!(
+            "The dataset is filtered by {}, which isn't defined",
+            role.name()
+        );
+        std::process::exit(1);
+    }
+    if let Some(seed) = options.seed {
+        config.seed = seed;
+    }
+    set_client(&config.api_base, &config.api_key());
+    let lobbies = (0..options.games)
+        .map(|index| (config.clone(), config.seed.wrapping_add(index as u64)))
+        .collect();
+    let batch_options = BatchOptions {
+        parallel: options.parallel,
+        max_phases: options.max_phases,
+        fail_fast: options.fail_fast,
+        out: Some(options.out.clone()),
+        reasoning: options.reasoning,
+        events: options.events,
+        quiet: false,
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let games = runtime.block_on(run_batch(lobbies, batch_options));
+
+    let summary = BatchSummary::new(games.iter().map(|game| game.summary.clone()).collect());
+    let path = options.out.join("summary.json");
+    let text = serde_json::to_string_pretty(&summary).unwrap();
+    if let Err(err) =
+        std::fs::create_dir_all(&options.out).and_then(|_| std::fs::write(&path, text))
+    {
+        eprintln!("Failed to write {}: {}", path.display(), err);
+    }
+    print!("{}", summary.render());
+    if let Some(path) = &options.dataset {
+        let saves = games
+            .into_iter()
+            .filter_map(|game| game.save)
+            .collect::<Vec<_>>();
+        match write_dataset(&saves, &options.dataset_filter, path) {
+            Ok(count) => println!("Wrote {} examples to {}", count, path.display()),
+            Err(err) => eprintln!("Failed to write {}: {}", path.display(), err),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use rust::llm::ai_interface::set_client;
+use rust::logging::init_headless;
+use rust::simulation::{BatchOptions, BatchSummary, run_batch};
+use rust::tournament::{Ratings, TournamentConfig};
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: tournament <tournament.json> [options]
+
+Options:
+    --rounds <n>        Rounds to play, each one is a game per model (default 1)
+    --parallel <n>      Games to run at the same time (default 1)
+    --out <dir>         Where transcripts, saves and the leaderboard go (default tournament)
+    --ratings <file>    Ratings to update (default ratings.json)
+    --max-phases <n>    Stop a game after this many days and nights
+
+Logs go to <out>/logs. LOG_LEVEL and FILE_LOG_LEVEL set how much is logged to the
+console and to the file, for example LOG_LEVEL=info or FILE_LOG_LEVEL=trace.";
+
+struct Options {
+    config: PathBuf,
+    rounds: usize,
+    parallel: usize,
+    out: PathBuf,
+    ratings: PathBuf,
+    max_phases: Option<u32>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        config: PathBuf::new(),
+        rounds: 1,
+        parallel: 1,
+        out: PathBuf::from("tournament"),
+        ratings: PathBuf::from("ratings.json"),
+        max_phases: None,
+    };
+    let mut config = None;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--rounds" => options.rounds = parse_number(&value("--rounds")?)?,
+            "--parallel" => options.parallel = parse_number(&value("--parallel")?)?,
+            "--out" => options.out = PathBuf::from(value("--out")?),
+            "--ratings" => options.ratings = PathBuf::from(value("--ratings")?),
+            "--max-phases" => options.max_phases = Some(parse_number(&value("--max-phases")?)?),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => config = Some(PathBuf::from(arg)),
+        }
+    }
+    options.config = config.ok_or_else(|| String::from("No tournament file given"))?;
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("{} is not a valid number", text))
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("{}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    init_headless(&options.out.join("logs"));
+    let config = match TournamentConfig::read(&options.config) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", options.config.display(), err);
+            std::process::exit(1);
+        }
+    };
+    let mut ratings = match Ratings::read(&options.ratings) {
+        Ok(ratings) => ratings,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", options.ratings.display(), err);
+            std::process::exit(1);
+        }
+    };
+    let schedule = config.schedule(options.rounds);
+    if let Some((lobby, _)) = schedule.first() {
+        set_client(&lobby.api_base, &lobby.api_key());
+    }
+    let batch_options = BatchOptions {
+        parallel: options.parallel,
+        max_phases: options.max_phases,
+        fail_fast: false,
+        out: Some(options.out.clone()),
+        reasoning: false,
+        events: false,
+        quiet: false,
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let games = runtime.block_on(run_batch(schedule, batch_options));
+
+    for game in &games {
+        ratings.record(&game.summary);
+    }
+    if let Err(err) = ratings.write(&options.ratings) {
+        eprintln!("Failed to write {}: {}", options.ratings.display(), err);
+    }
+    let summary = BatchSummary::new(games.into_iter().map(|game| game.summary).collect());
+    let leaderboard = ratings.leaderboard();
+    let path = options.out.join("leaderboard.md");
+    if let Err(err) =
+        std::fs::create_dir_all(&options.out).and_then(|_| std::fs::write(&path, &leaderboard))
+    {
+        eprintln!("Failed to write {}: {}", path.display(), err);
+    }
+    println!("{}", summary.render());
+    print!("{}", leaderboard);
+}
@@ -0,0 +1,125 @@
+use rust::experiment::{ExperimentConfig, VariantReport, render_reports};
+use rust::llm::ai_interface::set_client;
+use rust::logging::init_headless;
+use rust::simulation::{BatchOptions, run_batch};
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: variants <experiment.json> [options]
+
+Options:
+    --games <n>         Games per variant, played with the same seeds (default 1)
+    --parallel <n>      Games to run at the same time (default 1)
+    --out <dir>         Where transcripts, saves and the report go (default experiments)
+    --max-phases <n>    Stop a game after this many days and nights
+
+Logs go to <out>/logs. LOG_LEVEL and FILE_LOG_LEVEL set how much is logged to the
+console and to the file, for example LOG_LEVEL=info or FILE_LOG_LEVEL=trace.";
+
+struct Options {
+    config: PathBuf,
+    games: usize,
+    parallel: usize,
+    out: PathBuf,
+    max_phases: Option<u32>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        config: PathBuf::new(),
+        games: 1,
+        parallel: 1,
+        out: PathBuf::from("experiments"),
+        max_phases: None,
+    };
+    let mut config = None;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "--games" => options.games = parse_number(&value("--games")?)?,
+            "--parallel" => options.parallel = parse_number(&value("--parallel")?)?,
+            "--out" => options.out = PathBuf::from(value("--out")?),
+            "--max-phases" => options.max_phases = Some(parse_number(&value("--max-phases")?)?),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => config = Some(PathBuf::from(arg)),
+        }
+    }
+    options.config = config.ok_or_else(|| String::from("No experiment file given"))?;
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("{} is not a valid number", text))
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(err) => {
+            if !err.is_empty() {
+                eprintln!("{}\n", err);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    init_headless(&options.out.join("logs"));
+    let config = match ExperimentConfig::read(&options.config) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Could not read {}: {}", options.config.display(), err);
+            std::process::exit(1);
+        }
+    };
+    set_client(&config.lobby.api_base, &config.lobby.api_key());
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    for variant in &config.variants {
+        for name in config.lobby_for(variant).prompts.unknown_names() {
+            eprintln!(
+                "Warning: variant {} overrides {}, which isn't a prompt",
+                variant.name, name
+            );
+        }
+    }
+    let mut reports = Vec::new();
+    for variant in &config.variants {
+        let lobby = config.lobby_for(variant);
+        println!("Variant {}", variant.name);
+        let lobbies = (0..options.games)
+            .map(|index| (lobby.clone(), lobby.seed.wrapping_add(index as u64)))
+            .collect();
+        let batch_options = BatchOptions {
+            parallel: options.parallel,
+            max_phases: options.max_phases,
+            fail_fast: false,
+            out: Some(options.out.join(&variant.name)),
+            reasoning: false,
+            events: false,
+            quiet: false,
+        };
+        let games = runtime.block_on(run_batch(lobbies, batch_options));
+        reports.push(VariantReport::new(variant.name.clone(), &games));
+    }
+
+    let table = render_reports(&reports);
+    let report_path = options.out.join("report.md");
+    let summary_path = options.out.join("report.json");
+    if let Err(err) = std::fs::create_dir_all(&options.out)
+        .and_then(|_| std::fs::write(&report_path, &table))
+        .and_then(|_| {
+            std::fs::write(
+                &summary_path,
+                serde_json::to_string_pretty(&reports).unwrap(),
+            )
+        })
+    {
+        eprintln!(
+            "Failed to write the report to {}: {}",
+            options.out.display(),
+            err
+        );
+    }
+    print!("\n{}", table);
+}
@@ -0,0 +1,130 @@
+use crate::actor::parse_real_input;
+use crate::chat::ChatCommand;
+use crate::data::action::Action;
+use crate::data::context_entry::ContextEntry;
+use crate::data::event::GameEvent;
+use crate::export::analytics::GameAnalytics;
+use crate::frontend::{GameFrontend, PromptFuture, Recovery, RecoveryFuture, TurnFailure};
+use crate::game::GameState;
+use crate::game::events::EventSubscriber;
+use crate::game::save::SaveFile;
+use crate::{analytics_directory, load_message_scene};
+use godot::obj::WithBaseField;
+use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::channel;
+
+pub struct ChatFrontend {
+    pub command_sender: Sender<ChatCommand>,
+}
+
+impl ChatFrontend {
+    fn send(&self, command: ChatCommand) {
+        self.command_sender.send(command).unwrap();
+    }
+}
+
+/// Keeps the events of the chat's copy of the state up to date
+pub struct ChatEvents {
+    pub command_sender: Sender<ChatCommand>,
+}
+
+impl EventSubscriber for ChatEvents {
+    fn event_emitted(&self, _state: &GameState, event: &GameEvent) {
+        self.command_sender
+            .send(ChatCommand::EventEmitted(Box::new(event.clone())))
+            .unwrap();
+    }
+}
+
+impl GameFrontend for ChatFrontend {
+    fn actors_initialized(&self, state: &GameState) {
+        let actors = state.actors.clone();
+        // The only full copy, a resumed game starts with its whole context
+        self.send(ChatCommand::SyncState(Box::new(state.clone())));
+        self.send(ChatCommand::Closure(Box::new(move |chat| {
+            chat.spawn_visuals(&actors);
+            if actors
+                .iter()
+                .any(|actor| matches!(actor.kind, crate::actor::ActorKind::Real))
+            {
+                chat.base()
+                    .get_node_as::<godot::classes::PanelContainer>("Controls BG")
+                    .show();
+            }
+        })));
+    }
+
+    fn context_added(&self, _state: &GameState, entry: &ContextEntry) {
+        self.send(ChatCommand::ContextAdded(Box::new(entry.clone())));
+    }
+
+    fn actor_list_changed(&self, state: &GameState) {
+        self.send(ChatCommand::ActorsChanged(state.actors.clone()));
+    }
+
+    fn focus_actor(&self, actor_id: u8, content: String) {
+        self.send(ChatCommand::CameraFocus(actor_id, content));
+    }
+
+    fn checkpoint(&self, save: &SaveFile) {
+        self.send(ChatCommand::Checkpoint(Box::new(save.clone())));
+    }
+
+    fn game_ended(&self, save: &SaveFile) {
+        let analytics = GameAnalytics::from_save(save);
+        if let Err(err) = analytics.write(&analytics_directory()) {
+            tracing::warn!("Failed to write the game's analytics: {}", err);
+        }
+        self.send(ChatCommand::Closure(Box::new(move |chat| {
+            chat.show_game_over(&analytics.render());
+        })));
+    }
+
+    fn turn_failed(&self, failure: &TurnFailure) -> RecoveryFuture<'_> {
+        let text = failure.to_string();
+        let can_replace_model = failure.model_id.is_some();
+        Box::pin(async move {
+            let (sender, mut receiver) = channel::<Recovery>(1);
+            self.send(ChatCommand::Closure(Box::new(move |chat| {
+                chat.show_turn_failure(&text, can_replace_model, sender.clone());
+            })));
+            // Aborting to the menu cancels the game before this is answered
+            receiver.recv().await.unwrap_or(Recovery::Abort)
+        })
+    }
+
+    fn prompt_player(&self, _actor_id: u8, prompt: &str) -> PromptFuture<'_> {
+        let prompt = prompt.to_string();
+        Box::pin(async move {
+            loop {
+                let (sender, mut receiver) = channel::<Option<Action>>(1);
+                let prompt = prompt.clone();
+                self.send(ChatCommand::Closure(Box::new(move |chat| {
+                    let sender = sender.clone();
+                    let message = load_message_scene().instantiate_as::<godot::classes::Control>();
+                    message
+                        .get_node_as::<godot::classes::Label>("Container/Background/Sayer")
+                        .set_text("System");
+                    message
+                        .get_node_as::<godot::classes::RichTextLabel>("Container/Content")
+                        .set_text(&prompt);
+                    chat.get_message_list().add_child(&message);
+                    let send = chat.get_send_button();
+                    let mut input = chat.get_input_box();
+                    godot::task::spawn(async move {
+                        send.signals().pressed().to_future().await;
+                        let text = input.get_text();
+                        input.clear();
+                        sender
+                            .send(parse_real_input(&text.to_string()))
+                            .await
+                            .unwrap();
+                    });
+                })));
+                if let Some(action) = receiver.recv().await.unwrap() {
+                    return action;
+                }
+            }
+        })
+    }
+}
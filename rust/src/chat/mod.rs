@@ -1,8 +1,14 @@
-use crate::data::channel::Channel;
+use crate::actor::BaseActor;
+use crate::chat::frontend::{ChatEvents, ChatFrontend};
+use crate::configuration::{CONFIGURATION, Config};
 use crate::data::context_entry::{ContextEntry, SayerType};
-use crate::data::extra_data::ExtraData;
-use crate::game::Game;
-use crate::load_message_scene;
+use crate::data::event::GameEvent;
+use crate::data::roles::GameRole;
+use crate::game::autosave::Autosave;
+use crate::game::save::SaveFile;
+use crate::game::{Game, GameState};
+use crate::llm::ai_interface::set_client;
+use crate::{autosaves_directory, load_configuration_scene, load_message_scene, load_world_scene};
 use godot::classes::{AnimatableBody3D, Camera3D, Control, IControl};
 use godot::prelude::*;
 use std::collections::HashMap;
@@ -11,7 +17,9 @@ use std::sync::Arc;
 use std::sync::mpsc::{Receiver, channel};
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
+use tracing::Instrument;
 
+pub mod frontend;
 pub mod ui;
 pub mod visuals;
 
@@ -21,7 +29,14 @@ pub struct Chat {
     pub camera: Option<Gd<Camera3D>>,
     pub command_receiver: Option<Receiver<ChatCommand>>,
     pub player_nodes: HashMap<u8, Gd<AnimatableBody3D>>,
-    game_iteration: Option<JoinHandle<()>>,
+    /// Latest copy of the game's state, the game itself is locked while it iterates
+    pub state: GameState,
+    pub last_checkpoint: Option<SaveFile>,
+    /// The API key and URL, kept to play again with the same lobby
+    key_url_pair: (String, String),
+    start_at_night: bool,
+    /// Finishes with whether the game ended, or the failed turn it was aborted on
+    game_iteration: Option<JoinHandle<Result<bool, String>>>,
     game: Option<Arc<Mutex<Game>>>,
     base: Base<Control>,
 }
@@ -33,6 +48,10 @@ impl IControl for Chat {
             camera: None,
             command_receiver: None,
             player_nodes: HashMap::new(),
+            state: GameState::default(),
+            last_checkpoint: None,
+            key_url_pair: (String::new(), String::new()),
+            start_at_night: false,
             game_iteration: None,
             game: None,
             base,
@@ -46,9 +65,33 @@ impl IControl for Chat {
             self.handle_command(command);
         }
         if let Some(game_iteration) = &self.game_iteration {
-            if game_iteration.is_finished() {
-                self.game_iteration = None;
-            } else {
+            if !game_iteration.is_finished() {
+                return;
+            }
+            let game_iteration = self.game_iteration.take().unwrap();
+            // Already finished, so this doesn't block
+            let result = crate::tokio::AsyncRuntime::singleton()
+                .bind()
+                .runtime
+                .block_on(game_iteration);
+            let finished = match result.map_err(|err| {
+                if err.is_panic() {
+                    crate::simulation::panic_message(err.into_panic())
+                } else {
+                    err.to_string()
+                }
+            }) {
+                Ok(Ok(finished)) => finished,
+                Ok(Err(message)) | Err(message) => {
+                    // The game is somewhere in the middle of a phase, so it can't go on
+                    self.game = None;
+                    self.show_crash(&message);
+                    return;
+                }
+            };
+            if finished {
+                // The Game Over panel offers to play again or leave from here
+                self.game = None;
                 return;
             }
         }
@@ -60,19 +103,24 @@ impl IControl for Chat {
                     .runtime
                     .spawn(async move {
                         let mut game = game.lock().await;
-                        if let Some(end_result) = &game.end_result {
-                            Game::get_context_mut().push(ContextEntry {
-                                content: crate::prompts::general::game_end(end_result),
-                                sayer_type: SayerType::System,
-                                extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
-                            });
-                            game.send_on_behalf_of_chat(ChatCommand::RefreshContextWithActor);
-                            // TODO: Post game talk
-                            loop {
-                                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        let span = game.span();
+                        async move {
+                            // A loaded save can be of a game that already ended
+                            if game.end_result.is_none() {
+                                game.iterate().await;
+                            }
+                            if let Some(failure) = &game.aborted {
+                                return Err(failure.to_string());
                             }
+                            let finished = game.end_result.is_some();
+                            if finished {
+                                // TODO: Post game talk
+                                game.announce_end();
+                            }
+                            Ok(finished)
                         }
-                        game.iterate().await
+                        .instrument(span)
+                        .await
                     }),
             )
         }
@@ -81,65 +129,114 @@ impl IControl for Chat {
     fn ready(&mut self) {
         // This whole entire part needs a major cleanup
         self.camera = Some(self.get_world().get_node_as::<Camera3D>("Camera3D"));
-        let init_data = unsafe {
-            #[allow(static_mut_refs)]
-            if let Some(config) = crate::configuration::CONFIGURATION.as_ref() {
-                config
-            } else {
-                panic!("Configuration not initialized");
-            }
-        };
-        let actors = {
-            let mut actors = Vec::new();
-            for actor in &init_data.3 {
-                let base_actor = crate::actor::BaseActor {
-                    name: actor.name.clone(),
-                    id: actor.id,
-                    role: actor.role.clone(),
-                    extra_data: actor.extra_data.clone(),
-                    kind: match &actor.kind {
-                        crate::actor::ActorKind::Real => crate::actor::ActorKind::Real,
-                        crate::actor::ActorKind::Llm(ai_interface) => {
-                            crate::actor::ActorKind::Llm(crate::llm::ai_interface::AIInterface {
-                                model_id: ai_interface.model_id.clone(),
-                                owner_id: ai_interface.owner_id,
-                            })
-                        }
-                    },
-                    model_customization: actor.model_customization.clone(),
-                };
-                actors.push(base_actor);
-            }
-            actors
-        };
-        self.initialize(init_data.0, init_data.1.clone(), init_data.2, actors);
+        let init_data = CONFIGURATION
+            .lock()
+            .unwrap()
+            .take()
+            .expect("Configuration not initialized");
+        self.initialize(init_data);
     }
 }
 
 impl Chat {
-    pub fn initialize(
-        &mut self,
-        start_at_night: bool,
-        key_url_pair: (String, String),
-        playable_actor: Option<u8>,
-        actors: Vec<crate::actor::BaseActor>,
-    ) {
-        crate::llm::ai_interface::CLIENT.get_or_init(|| {
-            async_openai::Client::with_config(
-                async_openai::config::OpenAIConfig::default()
-                    .with_api_base(key_url_pair.1)
-                    .with_api_key(key_url_pair.0),
-            )
-        });
+    pub fn initialize(&mut self, config: Config) {
+        let Config {
+            start_at_night,
+            key_url_pair,
+            playable_actor,
+            actors,
+            seed,
+            resume,
+            cassette,
+            setup,
+            roles,
+        } = config;
+        set_client(&key_url_pair.1, &key_url_pair.0);
+        self.key_url_pair = key_url_pair;
+        self.start_at_night = start_at_night;
         let channel = channel::<ChatCommand>();
         self.command_receiver = Some(channel.1);
-        let mut game = Game::new(channel.0, playable_actor);
-        game.before_init(self);
-        game.init_actors(actors, self);
-        game.init_context(start_at_night);
+        #[cfg(feature = "development")]
+        self.setup_developer_window();
+        self.setup_menu();
+        self.get_seed_label().set_text(&format!("Seed: {}", seed));
+        let frontend = Box::new(ChatFrontend {
+            command_sender: channel.0.clone(),
+        });
+        let events = Box::new(ChatEvents {
+            command_sender: channel.0,
+        });
+        let mut game = if let Some(save) = resume {
+            // Every event so far comes with the state the resumed game syncs
+            let mut game = Game::from_save(frontend, save);
+            game.subscribe(events);
+            game
+        } else {
+            let mut game = Game::new(frontend, playable_actor, seed);
+            game.subscribe(events);
+            game.setup = setup;
+            game.state.roles = roles;
+            game.init_actors(actors);
+            game.init_context(start_at_night);
+            game
+        };
+        game.cassette = cassette;
+        game.autosave = Some(Autosave::new(&autosaves_directory()));
         self.game = Some(Arc::from(Mutex::from(game)));
     }
 
+    /// Cancels the running turn, if any, and drops the game
+    fn stop_game(&mut self) {
+        if let Some(game_iteration) = self.game_iteration.take() {
+            game_iteration.abort();
+        }
+        self.game = None;
+    }
+
+    /// Stops the game and goes back to the configuration screen
+    pub fn return_to_menu(&mut self) {
+        self.stop_game();
+        self.base()
+            .get_tree()
+            .unwrap()
+            .change_scene_to_packed(&load_configuration_scene());
+    }
+
+    /// Stops the game and deals a new one with the same players, models, setup and endpoint
+    pub fn play_again(&mut self) {
+        let Some(checkpoint) = &self.last_checkpoint else {
+            return;
+        };
+        let actors = checkpoint
+            .state
+            .actors
+            .iter()
+            .cloned()
+            .map(|mut actor| {
+                actor.role = GameRole::VILLAGER;
+                actor.extra_data.clear();
+                actor
+            })
+            .collect();
+        let config = Config {
+            start_at_night: self.start_at_night,
+            key_url_pair: self.key_url_pair.clone(),
+            playable_actor: checkpoint.playable_actor,
+            actors,
+            seed: rand::random(),
+            resume: None,
+            cassette: None,
+            setup: checkpoint.setup.clone(),
+            roles: checkpoint.state.roles.clone(),
+        };
+        self.stop_game();
+        *CONFIGURATION.lock().unwrap() = Some(config);
+        self.base()
+            .get_tree()
+            .unwrap()
+            .change_scene_to_packed(&load_world_scene());
+    }
+
     pub fn handle_command(&mut self, command: ChatCommand) {
         match command {
             ChatCommand::Closure(mut closure) => closure(self),
@@ -147,54 +244,69 @@ impl Chat {
                 self.get_current_text().set_text(&content.to_godot());
                 self.focus_camera_on_actor(id);
             }
-            ChatCommand::RefreshContextWithActor => {
-                let actors = Game::get_actors()
-                    .iter()
-                    .map(|actor| (actor.name.clone(), actor.id))
-                    .collect::<Vec<_>>();
-                let context = Game::get_context()
-                    .clone()
-                    .into_iter()
-                    .filter(|entry| {
-                        entry.available_for_actor(Game::get_actor_from_id(0).unwrap(), false)
-                    })
-                    .collect::<Vec<_>>();
+            ChatCommand::SyncState(state) => {
+                self.state = *state;
                 let mut messages = self.get_message_list();
                 for mut existing in messages.get_children().iter_shared() {
                     existing.queue_free();
                 }
-                for entry in &context {
-                    let message = load_message_scene().instantiate_as::<Control>();
-                    match entry.sayer_type {
-                        SayerType::Actor(id) => {
-                            let actor = actors.iter().find(|actor| actor.1 == id).unwrap();
-                            message
-                                .get_node_as::<godot::classes::Label>("Container/Background/Sayer")
-                                .set_text(&format!("{} (ID {})", actor.0, actor.1));
-                        }
-                        SayerType::System => {
-                            message
-                                .get_node_as::<godot::classes::Label>("Container/Background/Sayer")
-                                .set_text("System");
-                        }
-                    }
-                    message
-                        .get_node_as::<godot::classes::RichTextLabel>("Container/Content")
-                        .set_text(&entry.content);
-                    messages.add_child(&message);
+                for index in 0..self.state.context.len() {
+                    self.show_message(index);
                 }
+                self.refresh_actor_list();
             }
-            ChatCommand::RefreshActorList => {
-                let mut actor_list = self.get_actor_list();
-                for mut existing in actor_list.get_children().iter_shared() {
-                    existing.queue_free()
-                }
-                for actor in Game::get_nondead_actors() {
-                    let mut label = godot::classes::Label::new_alloc();
-                    label.set_text(&format!("{} (ID {})", actor.name, actor.id));
-                    actor_list.add_child(&label);
-                }
+            ChatCommand::Checkpoint(save) => self.last_checkpoint = Some(*save),
+            ChatCommand::ContextAdded(entry) => {
+                self.state.context.push(*entry);
+                self.show_message(self.state.context.len() - 1);
+            }
+            ChatCommand::ActorsChanged(actors) => {
+                self.state.actors = actors;
+                self.refresh_actor_list();
             }
+            ChatCommand::EventEmitted(event) => self.state.events.push(*event),
+        }
+    }
+
+    /// Adds the context entry at `index` to the message list if the player's actor can see it
+    fn show_message(&self, index: usize) {
+        let entry = &self.state.context[index];
+        if !entry.available_for_actor(
+            self.state.get_actor_from_id(0).unwrap(),
+            &self.state.roles,
+            false,
+        ) {
+            return;
+        }
+        let message = load_message_scene().instantiate_as::<Control>();
+        match entry.sayer_type {
+            SayerType::Actor(id) => {
+                let actor = self.state.get_actor_from_id(id).unwrap();
+                message
+                    .get_node_as::<godot::classes::Label>("Container/Background/Sayer")
+                    .set_text(&format!("{} (ID {})", actor.name, actor.id));
+            }
+            SayerType::System => {
+                message
+                    .get_node_as::<godot::classes::Label>("Container/Background/Sayer")
+                    .set_text("System");
+            }
+        }
+        message
+            .get_node_as::<godot::classes::RichTextLabel>("Container/Content")
+            .set_text(&entry.content);
+        self.get_message_list().add_child(&message);
+    }
+
+    fn refresh_actor_list(&self) {
+        let mut actor_list = self.get_actor_list();
+        for mut existing in actor_list.get_children().iter_shared() {
+            existing.queue_free()
+        }
+        for actor in self.state.get_nondead_actors() {
+            let mut label = godot::classes::Label::new_alloc();
+            label.set_text(&format!("{} (ID {})", actor.name, actor.id));
+            actor_list.add_child(&label);
         }
     }
 }
@@ -202,6 +314,11 @@ impl Chat {
 pub enum ChatCommand {
     Closure(Box<dyn FnMut(&mut Chat) + Send>),
     CameraFocus(u8, String),
-    RefreshContextWithActor,
-    RefreshActorList,
+    /// Replaces the whole copy of the state, only sent when the actors are first set up
+    SyncState(Box<GameState>),
+    Checkpoint(Box<SaveFile>),
+    ContextAdded(Box<ContextEntry>),
+    ActorsChanged(Vec<BaseActor>),
+    /// Sent by [`ChatEvents`]
+    EventEmitted(Box<GameEvent>),
 }
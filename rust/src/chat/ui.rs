@@ -1,14 +1,160 @@
-use godot::{classes::Window, obj::Gd};
+use godot::builtin::Vector2;
 use godot::{
-    classes::{Button, Control, Label, MeshInstance3D, Node3D, TextEdit, VBoxContainer},
+    classes::{AcceptDialog, HBoxContainer, LineEdit, Window},
+    obj::Gd,
+};
+use godot::{
+    classes::{Button, CheckBox, Control, Label, MeshInstance3D, Node3D, TextEdit, VBoxContainer},
     obj::WithBaseField,
 };
+use tokio::sync::mpsc::Sender;
 
 use crate::chat::Chat;
 use crate::data::context_entry::SayerType;
-use crate::load_message_scene;
+use crate::export::transcript::Transcript;
+use crate::frontend::Recovery;
+use crate::game::save::SaveFile;
+use crate::{load_message_scene, saves_directory, transcripts_directory};
 
 impl Chat {
+    /// Asks the user how to go on after a failed turn, answering through `sender`
+    pub fn show_turn_failure(
+        &mut self,
+        text: &str,
+        can_replace_model: bool,
+        sender: Sender<Recovery>,
+    ) {
+        let mut dialog = AcceptDialog::new_alloc();
+        dialog.set_title("Turn Failed");
+        dialog.set_ok_button_text("Retry");
+        let mut container = VBoxContainer::new_alloc();
+        let mut label = Label::new_alloc();
+        label.set_text(text);
+        label.set_autowrap_mode(godot::classes::text_server::AutowrapMode::WORD_SMART);
+        label.set_custom_minimum_size(Vector2::new(480.0, 0.0));
+        container.add_child(&label);
+        let mut skip = Button::new_alloc();
+        skip.set_text("Skip Turn");
+        container.add_child(&skip);
+        let mut model_row = HBoxContainer::new_alloc();
+        let mut model = LineEdit::new_alloc();
+        model.set_placeholder("Replacement model ID");
+        model.set_h_size_flags(godot::classes::control::SizeFlags::EXPAND_FILL);
+        model_row.add_child(&model);
+        let mut replace = Button::new_alloc();
+        replace.set_text("Replace Model");
+        model_row.add_child(&replace);
+        model_row.set_visible(can_replace_model);
+        container.add_child(&model_row);
+        let mut abort = Button::new_alloc();
+        abort.set_text("Abort to Menu");
+        container.add_child(&abort);
+        dialog.add_child(&container);
+
+        let answer = move |mut dialog: Gd<AcceptDialog>, recovery: Recovery| {
+            // Closing the dialog and picking a button both land here, only the first counts
+            let _ = sender.try_send(recovery);
+            dialog.queue_free();
+        };
+        let confirm = answer.clone();
+        let handle = dialog.clone();
+        dialog
+            .signals()
+            .confirmed()
+            .connect(move || confirm(handle.clone(), Recovery::Retry));
+        let cancel = answer.clone();
+        let handle = dialog.clone();
+        dialog
+            .signals()
+            .canceled()
+            .connect(move || cancel(handle.clone(), Recovery::Retry));
+        let skip_answer = answer.clone();
+        let handle = dialog.clone();
+        skip.signals()
+            .pressed()
+            .connect(move || skip_answer(handle.clone(), Recovery::Skip));
+        let handle = dialog.clone();
+        replace.signals().pressed().connect(move || {
+            let model_id = model.get_text().to_string().trim().to_string();
+            if !model_id.is_empty() {
+                answer(handle.clone(), Recovery::ReplaceModel(model_id));
+            }
+        });
+        let handle = dialog.clone();
+        abort.signals().pressed().connect(move || {
+            handle
+                .get_parent()
+                .unwrap()
+                .cast::<Chat>()
+                .bind_mut()
+                .return_to_menu();
+        });
+        self.base_mut().add_child(&dialog);
+        dialog.popup_centered();
+    }
+
+    /// For failures outside of a turn and turns the game was given up on, which leave nothing to retry
+    pub fn show_crash(&mut self, message: &str) {
+        let mut dialog = AcceptDialog::new_alloc();
+        dialog.set_title("Game Crashed");
+        dialog.set_text(&format!(
+            "The game stopped because of an error:\n{}\n\nThe log in user://logs has the details.",
+            message
+        ));
+        dialog.set_ok_button_text("Return to Menu");
+        let handle = dialog.clone();
+        let leave = move || {
+            handle
+                .get_parent()
+                .unwrap()
+                .cast::<Chat>()
+                .bind_mut()
+                .return_to_menu();
+        };
+        dialog.signals().confirmed().connect(leave.clone());
+        dialog.signals().canceled().connect(leave);
+        self.base_mut().add_child(&dialog);
+        dialog.popup_centered();
+    }
+
+    /// The end-of-game summary, with the ways to go on from there
+    pub fn show_game_over(&mut self, summary: &str) {
+        let mut dialog = AcceptDialog::new_alloc();
+        dialog.set_title("Game Over");
+        dialog.set_ok_button_text("Close");
+        let mut container = VBoxContainer::new_alloc();
+        let mut label = Label::new_alloc();
+        label.set_text(summary);
+        container.add_child(&label);
+        let mut play_again = Button::new_alloc();
+        play_again.set_text("Play Again With Same Lobby");
+        container.add_child(&play_again);
+        let mut leave = Button::new_alloc();
+        leave.set_text("Return to Menu");
+        container.add_child(&leave);
+        dialog.add_child(&container);
+        let handle = dialog.clone();
+        play_again.signals().pressed().connect(move || {
+            handle
+                .get_parent()
+                .unwrap()
+                .cast::<Chat>()
+                .bind_mut()
+                .play_again();
+        });
+        let handle = dialog.clone();
+        leave.signals().pressed().connect(move || {
+            handle
+                .get_parent()
+                .unwrap()
+                .cast::<Chat>()
+                .bind_mut()
+                .return_to_menu();
+        });
+        self.base_mut().add_child(&dialog);
+        dialog.popup_centered();
+    }
+
     pub fn setup_menu(&self) {
         self.base()
             .get_node_as::<Window>("Messages Window")
@@ -23,6 +169,14 @@ impl Chat {
         let open_messages = menu.get_node_as::<Button>("Background/Margin/Container/Open Messages");
         let developer_window =
             menu.get_node_as::<Button>("Background/Margin/Container/Developer Window");
+        let save_game = menu.get_node_as::<Button>("Background/Margin/Container/Save Game");
+        let export_transcript =
+            menu.get_node_as::<Button>("Background/Margin/Container/Export Transcript");
+        let include_reasoning =
+            menu.get_node_as::<CheckBox>("Background/Margin/Container/Include Reasoning");
+        let play_again = menu.get_node_as::<Button>("Background/Margin/Container/Play Again");
+        let return_to_menu =
+            menu.get_node_as::<Button>("Background/Margin/Container/Return To Menu");
         open_messages.signals().pressed().connect_self(|button| {
             let chat = button.get_node_as::<Chat>("../../../../..");
             chat.get_node_as::<Window>("Messages Window").show();
@@ -31,6 +185,56 @@ impl Chat {
             let chat = button.get_node_as::<Chat>("../../../../..");
             chat.get_node_as::<Window>("Developer Window").show();
         });
+        save_game.signals().pressed().connect_self(|button| {
+            let chat = button.get_node_as::<Chat>("../../../../..");
+            let chat = chat.bind();
+            let Some(save) = &chat.last_checkpoint else {
+                return;
+            };
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let path = saves_directory().join(format!("save-{}.json", timestamp));
+            match save.write(&path) {
+                Ok(()) => button.set_text("Game Saved"),
+                Err(err) => tracing::warn!("Failed to save the game: {}", err),
+            }
+        });
+        export_transcript
+            .signals()
+            .pressed()
+            .connect_self(move |button| {
+                let chat = button.get_node_as::<Chat>("../../../../..");
+                let chat = chat.bind();
+                let Some(checkpoint) = &chat.last_checkpoint else {
+                    return;
+                };
+                // The checkpoint is from the start of the phase, the synced state is newer
+                let save = SaveFile {
+                    state: chat.state.clone(),
+                    ..checkpoint.clone()
+                };
+                let transcript = Transcript::from_save(&save, include_reasoning.is_pressed());
+                match transcript.write_all(&transcripts_directory()) {
+                    Ok(_) => button.set_text("Transcript Exported"),
+                    Err(err) => {
+                        tracing::warn!("Failed to export the transcript: {}", err)
+                    }
+                }
+            });
+        play_again.signals().pressed().connect_self(|button| {
+            button
+                .get_node_as::<Chat>("../../../../..")
+                .bind_mut()
+                .play_again();
+        });
+        return_to_menu.signals().pressed().connect_self(|button| {
+            button
+                .get_node_as::<Chat>("../../../../..")
+                .bind_mut()
+                .return_to_menu();
+        });
         close.signals().pressed().connect(move || {
             menu.hide();
         });
@@ -58,7 +262,8 @@ impl Chat {
             .get_node_as::<godot::classes::OptionButton>("Root UI/Control Panel/ID Select");
         id_select.signals().pressed().connect_self(|button| {
             button.clear();
-            for actor in crate::game::Game::get_actors() {
+            let chat = button.get_node_as::<Chat>("../../../../..");
+            for actor in &chat.bind().state.actors {
                 button.add_item(&format!("{} (ID {})", actor.name, actor.id));
             }
         });
@@ -67,13 +272,18 @@ impl Chat {
             .signals()
             .pressed()
             .connect(move || {
-                let context = crate::game::Game::get_context()
-                    .clone()
-                    .into_iter()
+                let chat = developer.get_node_as::<Chat>("../..");
+                let chat = chat.bind();
+                let context = chat
+                    .state
+                    .context
+                    .iter()
                     .filter(|entry| {
                         entry.available_for_actor(
-                            crate::game::Game::get_actor_from_id(id_select.get_selected_id() as u8)
+                            chat.state
+                                .get_actor_from_id(id_select.get_selected_id() as u8)
                                 .unwrap(),
+                            &chat.state.roles,
                             developer
                                 .get_node_as::<godot::classes::CheckBox>(
                                     "Root UI/Control Panel/Include Raw",
@@ -81,6 +291,7 @@ impl Chat {
                                 .is_pressed(),
                         )
                     })
+                    .cloned()
                     .collect::<Vec<_>>();
                 let mut messages =
                     developer.get_node_as::<VBoxContainer>("Root UI/Scroll/Messages");
@@ -91,10 +302,7 @@ impl Chat {
                     let message = load_message_scene().instantiate_as::<Control>();
                     match entry.sayer_type {
                         SayerType::Actor(id) => {
-                            let actor = crate::game::Game::get_actors()
-                                .iter()
-                                .find(|actor| actor.id == id)
-                                .unwrap();
+                            let actor = chat.state.get_actor_from_id(id).unwrap();
                             message
                                 .get_node_as::<Label>("Container/Background/Sayer")
                                 .set_text(&format!("{} (ID {})", actor.name, actor.id));
@@ -146,6 +354,11 @@ impl Chat {
         self.base().get_node_as::<Control>("Menu")
     }
 
+    pub fn get_seed_label(&self) -> Gd<Label> {
+        self.get_menu()
+            .get_node_as::<Label>("Background/Margin/Container/Seed")
+    }
+
     pub fn get_current_text(&self) -> Gd<Label> {
         self.base().get_node_as::<Label>("Text BG/Current Text")
     }
@@ -73,13 +73,15 @@ impl Chat {
                 .cast::<godot::classes::Texture2D>();
             body.get_node_as::<Sprite3D>("Head/Image")
                 .set_texture(&texture);
+            let [r, g, b, a] = actor.model_customization.color;
+            let color = godot::builtin::Color::from_rgba(r, g, b, a);
             let mut material = godot::classes::StandardMaterial3D::new_gd();
-            material.set_albedo(actor.model_customization.color);
+            material.set_albedo(color);
             body.set_material_override(&material);
             for child in body.get_children().iter_shared() {
                 if let Ok(mut child) = child.try_cast::<godot::classes::MeshInstance3D>() {
                     let mut material = godot::classes::StandardMaterial3D::new_gd();
-                    material.set_albedo(actor.model_customization.color);
+                    material.set_albedo(color);
                     child.set_material_override(&material);
                 }
             }
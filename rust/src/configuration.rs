@@ -1,23 +1,53 @@
-use crate::load_world_scene;
+use crate::actor::{ActorKind, ModelCustomization};
+use crate::data::roles::{RoleBook, load_roles};
+use crate::game::autosave::Autosave;
+use crate::game::fork::ActorOverride;
+use crate::game::save::SaveFile;
+use crate::game::setup::{GameSetup, builtin_presets, merge_presets, read_presets, write_presets};
+use crate::llm::cassette::Cassette;
+use crate::prompts::PromptOverrides;
+use crate::{
+    autosaves_directory, cassettes_directory, load_world_scene, presets_path, roles_directory,
+    saves_directory,
+};
+use async_openai::types::chat::ReasoningEffort;
 use godot::{
-    classes::{Button, CheckBox, Control, IControl, Label, LineEdit, VBoxContainer},
+    classes::{
+        AcceptDialog, Button, CheckBox, ConfirmationDialog, Control, FileDialog, HBoxContainer,
+        IControl, Label, LineEdit, OptionButton, SpinBox, TextEdit, VBoxContainer,
+        control::SizeFlags, file_dialog,
+    },
     prelude::*,
 };
+use std::path::Path;
+use std::sync::Mutex;
 
-pub type Config = (
-    bool,
-    (String, String),
-    Option<u8>,
-    Vec<crate::actor::BaseActor>,
-);
+pub struct Config {
+    pub start_at_night: bool,
+    pub key_url_pair: (String, String),
+    pub playable_actor: Option<u8>,
+    pub actors: Vec<crate::actor::BaseActor>,
+    pub seed: u64,
+    /// Continue this save instead of dealing a new game
+    pub resume: Option<SaveFile>,
+    pub cassette: Option<Cassette>,
+    pub setup: GameSetup,
+    /// What a new game is dealt from, a resumed one keeps the roles of its save
+    pub roles: RoleBook,
+}
 
-pub static mut CONFIGURATION: Option<Config> = None;
+/// Handed from the configuration screen to the `Chat` that starts the game
+pub static CONFIGURATION: Mutex<Option<Config>> = Mutex::new(None);
 
 #[derive(GodotClass)]
 #[class(base = Control)]
 struct Configuration {
     model_pool: Vec<ModelNameID>,
     selected_entry: Option<Gd<ActorEntry>>,
+    /// The built-in setups first, then the saved presets, in the order of the setup picker
+    setups: Vec<GameSetup>,
+    /// The built-in roles and those in [`roles_directory`]
+    roles: RoleBook,
     base: Base<Control>,
 }
 
@@ -30,15 +60,39 @@ impl IControl for Configuration {
                 display_name: "DeepSeek".to_string(),
                 model_customization: ModelCustomization {
                     sprite_path: "res://images/deepseek.png".to_string(),
-                    color: Color::LIGHT_BLUE,
+                    color: rgba(Color::LIGHT_BLUE),
                 },
             }],
             selected_entry: None,
+            setups: Vec::new(),
+            roles: RoleBook::default(),
             base,
         }
     }
 
     fn ready(&mut self) {
+        crate::init_logging();
+        let roles_directory = roles_directory();
+        match load_roles(&roles_directory) {
+            Ok(definitions) => {
+                if !definitions.is_empty() {
+                    let names = definitions
+                        .iter()
+                        .map(|definition| definition.name.clone())
+                        .collect::<Vec<_>>();
+                    tracing::info!("Loaded the roles {}", names.join(", "));
+                }
+                self.roles.extend(definitions);
+            }
+            Err(err) => show_alert(
+                &self.to_gd().upcast::<Node>(),
+                &format!(
+                    "Could not load the roles in {}: {}",
+                    roles_directory.display(),
+                    err
+                ),
+            ),
+        }
         let mut api_key = self
             .base()
             .get_node_as::<LineEdit>("Root UI/Main Controls/API Key");
@@ -108,12 +162,101 @@ impl IControl for Configuration {
         let start_at_night = self
             .base()
             .get_node_as::<CheckBox>("Root UI/Main Controls/Start At Night");
+        let seed = self
+            .base()
+            .get_node_as::<LineEdit>("Root UI/Main Controls/Seed");
+        let mut continue_button = self
+            .base()
+            .get_node_as::<Button>("Root UI/Main Controls/Continue");
+        if let Some(save) = Autosave::new(&autosaves_directory()).latest_unfinished() {
+            continue_button.set_visible(true);
+            let continue_key = api_key.clone();
+            let continue_url = api_url.clone();
+            continue_button
+                .signals()
+                .pressed()
+                .connect_self(move |button| {
+                    start_from_save(
+                        button.clone().upcast::<Node>(),
+                        &continue_key,
+                        &continue_url,
+                        save.clone(),
+                    );
+                });
+        }
+        let load_key = api_key.clone();
+        let load_url = api_url.clone();
+        let load_button = self
+            .base()
+            .get_node_as::<Button>("Root UI/Main Controls/Load");
+        let picker_parent = load_button.clone().upcast::<Node>();
+        load_button.signals().pressed().connect(move || {
+            let api_key = load_key.clone();
+            let api_url = load_url.clone();
+            open_save_picker(&picker_parent, move |node, save| {
+                start_from_save(node, &api_key, &api_url, save);
+            });
+        });
+        let fork_key = api_key.clone();
+        let fork_url = api_url.clone();
+        let fork_button = self
+            .base()
+            .get_node_as::<Button>("Root UI/Main Controls/Fork");
+        let picker_parent = fork_button.clone().upcast::<Node>();
+        fork_button.signals().pressed().connect(move || {
+            let api_key = fork_key.clone();
+            let api_url = fork_url.clone();
+            open_save_picker(&picker_parent, move |node, save| {
+                open_fork_dialog(node, api_key.clone(), api_url.clone(), save);
+            });
+        });
+        self.refresh_setups();
+        let load_presets_button = self
+            .base()
+            .get_node_as::<Button>("Root UI/Main Controls/Load Presets");
+        let picker_parent = load_presets_button.clone().upcast::<Node>();
+        load_presets_button.signals().pressed().connect(move || {
+            open_presets_picker(&picker_parent);
+        });
+        let setup_picker = self
+            .base()
+            .get_node_as::<OptionButton>("Root UI/Main Controls/Setup");
+        let cassette_mode = self
+            .base()
+            .get_node_as::<OptionButton>("Root UI/Main Controls/Cassette Mode");
+        let cassette_path = self
+            .base()
+            .get_node_as::<LineEdit>("Root UI/Main Controls/Cassette Path");
+        let root = self.to_gd().upcast::<Node>();
         let actor_list = self.obtain_actor_list();
         self.base()
             .get_node_as::<Button>("Root UI/Main Controls/Padding/Begin")
             .signals()
             .pressed()
             .connect_self(move |config| {
+                let configuration = root.clone().cast::<Configuration>();
+                let (setup, roles) = {
+                    let configuration = configuration.bind();
+                    (
+                        configuration
+                            .setups
+                            .get(setup_picker.get_selected().max(0) as usize)
+                            .cloned()
+                            .unwrap_or_default(),
+                        configuration.roles.clone(),
+                    )
+                };
+                if let Err(err) = setup.validate(actor_list.get_child_count() as usize, &roles) {
+                    show_alert(&root, &err);
+                    return;
+                }
+                let cassette = match build_cassette(&cassette_mode, &cassette_path) {
+                    Ok(cassette) => cassette,
+                    Err(err) => {
+                        show_alert(&root, &format!("Could not open the cassette: {}", err));
+                        return;
+                    }
+                };
                 let mut tree = config.get_tree().unwrap();
                 tree.change_scene_to_packed(&load_world_scene());
                 let mut playable_actor: Option<u8> = None;
@@ -127,12 +270,12 @@ impl IControl for Configuration {
                                 actors.push(crate::actor::BaseActor {
                                     name: name.clone(),
                                     id: index as u8,
-                                    role: crate::data::roles::GameRole::Villager,
+                                    role: crate::data::roles::GameRole::VILLAGER,
                                     extra_data: vec![],
                                     kind: crate::actor::ActorKind::Real,
                                     model_customization: ModelCustomization {
                                         sprite_path: "res://images/user.png".to_string(),
-                                        color: Color::WHITE,
+                                        color: rgba(Color::WHITE),
                                     },
                                 });
                                 playable_actor = Some(index as u8);
@@ -141,12 +284,14 @@ impl IControl for Configuration {
                                 actors.push(crate::actor::BaseActor {
                                     name: model_name_id.display_name.clone(),
                                     id: index as u8,
-                                    role: crate::data::roles::GameRole::Villager,
+                                    role: crate::data::roles::GameRole::VILLAGER,
                                     extra_data: vec![],
                                     kind: crate::actor::ActorKind::Llm(
                                         crate::llm::ai_interface::AIInterface {
                                             model_id: model_name_id.model_id.clone(),
                                             owner_id: index as u8,
+                                            instructions: None,
+                                            reasoning_effort: None,
                                         },
                                     ),
                                     model_customization: model_name_id.model_customization.clone(),
@@ -156,17 +301,25 @@ impl IControl for Configuration {
                     }
                     actors
                 };
-                unsafe {
-                    CONFIGURATION = Some((
-                        start_at_night.is_pressed(),
-                        (
-                            api_key.get_text().to_string().trim().to_string(),
-                            api_url.get_text().to_string(),
-                        ),
-                        playable_actor,
-                        actors,
-                    ));
-                }
+                *CONFIGURATION.lock().unwrap() = Some(Config {
+                    start_at_night: start_at_night.is_pressed(),
+                    key_url_pair: (
+                        api_key.get_text().to_string().trim().to_string(),
+                        api_url.get_text().to_string(),
+                    ),
+                    playable_actor,
+                    actors,
+                    seed: seed
+                        .get_text()
+                        .to_string()
+                        .trim()
+                        .parse::<u64>()
+                        .unwrap_or_else(|_| rand::random()),
+                    resume: None,
+                    cassette,
+                    setup,
+                    roles,
+                });
             });
     }
 }
@@ -187,7 +340,7 @@ impl Configuration {
                 display_name: "Use My ID Instead".to_string(),
                 model_customization: ModelCustomization {
                     sprite_path: "res://images/openai.png".to_string(),
-                    color: Color::WHITE,
+                    color: rgba(Color::WHITE),
                 },
             };
         }
@@ -199,6 +352,252 @@ impl Configuration {
     pub fn return_model(&mut self, model: ModelNameID) {
         self.model_pool.push(model);
     }
+
+    /// Reloads the saved presets into the setup picker
+    fn refresh_setups(&mut self) {
+        let mut setups = builtin_presets();
+        match read_presets(&presets_path()) {
+            Ok(presets) => setups.extend(presets),
+            Err(err) => tracing::warn!("Could not read the presets: {}", err),
+        }
+        let mut picker = self
+            .base()
+            .get_node_as::<OptionButton>("Root UI/Main Controls/Setup");
+        picker.clear();
+        for setup in &setups {
+            picker.add_item(&format!("{} ({})", setup.name, setup.describe()));
+        }
+        picker.select(0);
+        self.setups = setups;
+    }
+}
+
+/// Adds the presets of a file, such as the ones the balance tool writes, to the saved presets
+fn open_presets_picker(parent: &Gd<Node>) {
+    let mut dialog = FileDialog::new_alloc();
+    dialog.set_file_mode(file_dialog::FileMode::OPEN_FILE);
+    dialog.set_access(file_dialog::Access::FILESYSTEM);
+    dialog.add_filter("*.json");
+    let node = parent.clone();
+    dialog
+        .signals()
+        .file_selected()
+        .connect(move |path: GString| {
+            let loaded = match read_presets(Path::new(&path.to_string())) {
+                Ok(loaded) => loaded,
+                Err(err) => {
+                    show_alert(&node, &format!("Could not read the presets: {}", err));
+                    return;
+                }
+            };
+            let mut presets = read_presets(&presets_path()).unwrap_or_default();
+            merge_presets(&mut presets, loaded);
+            if let Err(err) = write_presets(&presets, &presets_path()) {
+                show_alert(&node, &format!("Could not save the presets: {}", err));
+                return;
+            }
+            node.get_node_as::<Configuration>("../../..")
+                .bind_mut()
+                .refresh_setups();
+        });
+    parent.clone().add_child(&dialog);
+    dialog.popup_centered();
+}
+
+fn show_alert(parent: &Gd<Node>, text: &str) {
+    let mut alert = AcceptDialog::new_alloc();
+    alert.set_text(text);
+    parent.clone().add_child(&alert);
+    alert.popup_centered();
+}
+
+fn open_save_picker(parent: &Gd<Node>, mut on_picked: impl FnMut(Gd<Node>, SaveFile) + 'static) {
+    let mut dialog = FileDialog::new_alloc();
+    dialog.set_file_mode(file_dialog::FileMode::OPEN_FILE);
+    dialog.set_access(file_dialog::Access::FILESYSTEM);
+    dialog.set_current_dir(saves_directory().to_string_lossy().as_ref());
+    dialog.add_filter("*.json");
+    let node = parent.clone();
+    dialog
+        .signals()
+        .file_selected()
+        .connect(
+            move |path: GString| match SaveFile::read(Path::new(&path.to_string())) {
+                Ok(save) => on_picked(node.clone(), save),
+                Err(err) => show_alert(&node, &err.to_string()),
+            },
+        );
+    parent.clone().add_child(&dialog);
+    dialog.popup_centered();
+}
+
+fn start_from_save(node: Gd<Node>, api_key: &Gd<LineEdit>, api_url: &Gd<LineEdit>, save: SaveFile) {
+    *CONFIGURATION.lock().unwrap() = Some(Config {
+        start_at_night: false,
+        key_url_pair: (
+            api_key.get_text().to_string().trim().to_string(),
+            api_url.get_text().to_string(),
+        ),
+        playable_actor: save.playable_actor,
+        actors: vec![],
+        seed: save.seed,
+        resume: Some(save),
+        cassette: None,
+        setup: GameSetup::default(),
+        roles: RoleBook::default(),
+    });
+    node.get_tree()
+        .unwrap()
+        .change_scene_to_packed(&load_world_scene());
+}
+
+/// Offered by the fork dialog after "Keep"
+const REASONING_EFFORTS: [(&str, ReasoningEffort); 4] = [
+    ("Minimal", ReasoningEffort::Minimal),
+    ("Low", ReasoningEffort::Low),
+    ("Medium", ReasoningEffort::Medium),
+    ("High", ReasoningEffort::High),
+];
+
+/// Lets the user pick where to branch a save, and the models, instructions,
+/// reasoning effort and prompt overrides the branch plays with
+fn open_fork_dialog(
+    parent: Gd<Node>,
+    api_key: Gd<LineEdit>,
+    api_url: Gd<LineEdit>,
+    save: SaveFile,
+) {
+    let mut dialog = ConfirmationDialog::new_alloc();
+    dialog.set_title("Fork Game");
+    let mut container = VBoxContainer::new_alloc();
+    let mut label = Label::new_alloc();
+    label.set_text(&format!(
+        "Fork at context entry (0-{}), play resumes from the start of its phase",
+        save.state.context.len()
+    ));
+    container.add_child(&label);
+    let mut context_index = SpinBox::new_alloc();
+    context_index.set_max(save.state.context.len() as f64);
+    context_index.set_value(save.state.context.len() as f64);
+    container.add_child(&context_index);
+    let mut actor_inputs = Vec::new();
+    for actor in &save.state.actors {
+        if let ActorKind::Llm(llm) = &actor.kind {
+            let mut row = HBoxContainer::new_alloc();
+            let mut name = Label::new_alloc();
+            name.set_text(&format!("{} (ID {})", actor.name, actor.id));
+            row.add_child(&name);
+            let mut model = LineEdit::new_alloc();
+            model.set_text(&llm.model_id);
+            model.set_h_size_flags(SizeFlags::EXPAND_FILL);
+            row.add_child(&model);
+            let mut instructions = LineEdit::new_alloc();
+            instructions.set_text(llm.instructions.as_deref().unwrap_or_default());
+            instructions.set_placeholder("Instructions");
+            instructions.set_h_size_flags(SizeFlags::EXPAND_FILL);
+            row.add_child(&instructions);
+            let mut reasoning_effort = OptionButton::new_alloc();
+            reasoning_effort.add_item("Keep Reasoning Effort");
+            for (label, _) in &REASONING_EFFORTS {
+                reasoning_effort.add_item(*label);
+            }
+            row.add_child(&reasoning_effort);
+            container.add_child(&row);
+            actor_inputs.push(ForkActorInputs {
+                actor_id: actor.id,
+                model_id: llm.model_id.clone(),
+                instructions: llm.instructions.clone().unwrap_or_default(),
+                model,
+                instructions_input: instructions,
+                reasoning_effort,
+            });
+        }
+    }
+    let mut prompts_label = Label::new_alloc();
+    prompts_label.set_text("Prompt overrides, as a JSON object of templates by prompt name");
+    container.add_child(&prompts_label);
+    let mut prompts = TextEdit::new_alloc();
+    prompts.set_text(&serde_json::to_string_pretty(&save.prompts).unwrap());
+    prompts.set_custom_minimum_size(Vector2::new(0.0, 160.0));
+    container.add_child(&prompts);
+    dialog.add_child(&container);
+    let node = parent.clone();
+    dialog.signals().confirmed().connect(move || {
+        let prompt_overrides: PromptOverrides =
+            match serde_json::from_str(&prompts.get_text().to_string()) {
+                Ok(prompt_overrides) => prompt_overrides,
+                Err(err) => {
+                    show_alert(
+                        &node,
+                        &format!("The prompt overrides are malformed: {}", err),
+                    );
+                    return;
+                }
+            };
+        let unknown = prompt_overrides.unknown_names();
+        if !unknown.is_empty() {
+            show_alert(&node, &format!("No prompt is named {}", unknown.join(", ")));
+            return;
+        }
+        let overrides = actor_inputs
+            .iter()
+            .map(ForkActorInputs::to_override)
+            .collect::<Vec<_>>();
+        let mut fork = match save.fork(context_index.get_value() as usize, &overrides) {
+            Ok(fork) => fork,
+            Err(err) => {
+                show_alert(&node, &err.to_string());
+                return;
+            }
+        };
+        fork.prompts = prompt_overrides;
+        let path = saves_directory().join(format!("fork-{}.json", fork.game_id));
+        if let Err(err) = fork.write(&path) {
+            tracing::warn!("Failed to write the fork: {}", err);
+        }
+        start_from_save(node.clone(), &api_key, &api_url, fork);
+    });
+    parent.clone().add_child(&dialog);
+    dialog.popup_centered();
+}
+
+/// One LLM actor's row of the fork dialog
+struct ForkActorInputs {
+    actor_id: u8,
+    model_id: String,
+    instructions: String,
+    model: Gd<LineEdit>,
+    instructions_input: Gd<LineEdit>,
+    reasoning_effort: Gd<OptionButton>,
+}
+
+impl ForkActorInputs {
+    /// Only what was changed is overridden
+    fn to_override(&self) -> ActorOverride {
+        let model_id = self.model.get_text().to_string().trim().to_string();
+        let instructions = self.instructions_input.get_text().to_string();
+        ActorOverride {
+            actor_id: self.actor_id,
+            model_id: (model_id != self.model_id).then_some(model_id),
+            instructions: (instructions != self.instructions).then_some(instructions),
+            reasoning_effort: match self.reasoning_effort.get_selected() {
+                index if index > 0 => Some(REASONING_EFFORTS[index as usize - 1].1.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+fn build_cassette(
+    mode: &Gd<OptionButton>,
+    path: &Gd<LineEdit>,
+) -> std::io::Result<Option<Cassette>> {
+    let path = cassettes_directory().join(path.get_text().to_string().trim());
+    match mode.get_selected_id() {
+        1 => Cassette::record(&path).map(Some),
+        2 => Cassette::replay(&path).map(Some),
+        _ => Ok(None),
+    }
 }
 
 fn construct_entry(blueprint: ActorBlueprint) -> Gd<ActorEntry> {
@@ -260,8 +659,6 @@ pub struct ModelNameID {
     pub model_customization: ModelCustomization,
 }
 
-#[derive(Clone)]
-pub struct ModelCustomization {
-    pub sprite_path: String,
-    pub color: Color,
+fn rgba(color: Color) -> [f32; 4] {
+    [color.r, color.g, color.b, color.a]
 }
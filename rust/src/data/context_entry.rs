@@ -1,13 +1,15 @@
 use crate::actor::BaseActor;
 use crate::data::channel::Channel;
 use crate::data::extra_data::ExtraData;
-use crate::game::Game;
+use crate::data::roles::{RoleAlignment, RoleBook};
+use crate::game::GameState;
 use async_openai::types::chat::{
     ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
     ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ContextEntry {
     pub content: String,
     pub sayer_type: SayerType,
@@ -15,10 +17,14 @@ pub struct ContextEntry {
 }
 
 impl ContextEntry {
-    pub fn to_chat_message(&self, for_actor_id: u8) -> Option<ChatCompletionRequestMessage> {
-        let actor = Game::get_actor_from_id(for_actor_id)?;
+    pub fn to_chat_message(
+        &self,
+        state: &GameState,
+        for_actor_id: u8,
+    ) -> Option<ChatCompletionRequestMessage> {
+        let actor = state.get_actor_from_id(for_actor_id)?;
 
-        if !self.available_for_actor(actor, false) {
+        if !self.available_for_actor(actor, &state.roles, false) {
             return None;
         }
 
@@ -48,15 +54,18 @@ impl ContextEntry {
         }
     }
 
-    pub fn available_for_actor(&self, actor: &BaseActor, include_raw: bool) -> bool {
+    /// `roles` are those of the game, which define the actor's
+    pub fn available_for_actor(
+        &self,
+        actor: &BaseActor,
+        roles: &RoleBook,
+        include_raw: bool,
+    ) -> bool {
         self.extra_data.iter().all(|data| match data {
             ExtraData::WhisperMetadata { from, to } => !(actor.id == *from || actor.id == *to),
             ExtraData::SaidInChannel(channel) => match channel {
                 Channel::Global => true,
-                Channel::Mafia => matches!(
-                    actor.role.alignment(),
-                    crate::data::roles::RoleAlignment::Mafia
-                ),
+                Channel::Mafia => roles[&actor.role].alignment == RoleAlignment::Mafia,
                 Channel::ToSelf(id) => *id == actor.id,
                 Channel::Raw(id) => include_raw && *id == actor.id,
             },
@@ -65,7 +74,7 @@ impl ContextEntry {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum SayerType {
     Actor(u8),
     System,
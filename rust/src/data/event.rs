@@ -0,0 +1,134 @@
+use crate::data::channel::Channel;
+use crate::data::roles::{NightActionKind, RoleAlignment, WinCondition};
+use crate::game::EndResult;
+use crate::game::beliefs::ProbePoint;
+use async_openai::types::chat::FunctionCall;
+use serde::{Deserialize, Serialize};
+
+/// A typed record of something that happened in the game.
+/// The context holds the same information as prose for the LLMs, tools should read these instead.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum GameEvent {
+    PhaseStarted {
+        is_night: bool,
+        /// Counts days and nights separately, starting at 0
+        number: u8,
+    },
+    Spoke {
+        actor_id: u8,
+        channel: Channel,
+        message: String,
+    },
+    Abstained {
+        actor_id: u8,
+        channel: Channel,
+    },
+    Whispered {
+        from: u8,
+        to: u8,
+        message: String,
+    },
+    Tagged {
+        actor_id: u8,
+        target: u8,
+    },
+    Voted {
+        voter: u8,
+        channel: Channel,
+        target: Option<u8>,
+        comment: Option<String>,
+    },
+    /// `target` is `None` when nobody got a majority
+    VoteEnded {
+        channel: Channel,
+        target: Option<u8>,
+    },
+    Protected {
+        doctor: u8,
+        target: u8,
+    },
+    Investigated {
+        sheriff: u8,
+        target: u8,
+        /// What the investigation found, which some roles disguise
+        alignment: RoleAlignment,
+    },
+    Blocked {
+        blocker: u8,
+        target: u8,
+    },
+    /// The mafia or a killing role picked a target that was protected
+    KillPrevented {
+        target: u8,
+    },
+    Killed {
+        actor_id: u8,
+        cause: DeathCause,
+    },
+    /// An LLM answered a prompt. Its request can be rebuilt from the context up to `context_len`.
+    Decided {
+        actor_id: u8,
+        model_id: String,
+        context_len: usize,
+        prompt: String,
+        /// Names of the tools offered, see [`crate::llm::tools::make_tool_by_name`]
+        tools: Vec<String>,
+        tool_calls: Vec<FunctionCall>,
+    },
+    /// A player met their role's own win condition. The game goes on.
+    WinConditionMet {
+        actor_id: u8,
+        condition: WinCondition,
+    },
+    /// Every night action that was picked, in the order they were resolved
+    NightResolved {
+        actions: Vec<ResolvedAction>,
+    },
+    GameEnded {
+        result: EndResult,
+    },
+    /// An LLM's private answer to a belief probe. It is never added to the context.
+    BeliefsProbed {
+        actor_id: u8,
+        model_id: String,
+        point: ProbePoint,
+        beliefs: Vec<Belief>,
+        reasoning: String,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Belief {
+    pub target: u8,
+    /// Clamped to 0 to 1
+    pub mafia_probability: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ResolvedAction {
+    /// `None` for the Mafia's kill
+    pub actor_id: Option<u8>,
+    pub kind: NightActionKind,
+    pub target: u8,
+    pub outcome: ActionOutcome,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ActionOutcome {
+    Succeeded,
+    /// Everyone who would have carried it out was blocked
+    Blocked,
+    /// A kill on a protected target
+    Prevented,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum DeathCause {
+    VotedOut,
+    Mafia,
+    /// By a role's own night action
+    NightAction {
+        attacker: u8,
+    },
+}
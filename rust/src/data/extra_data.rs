@@ -1,9 +1,25 @@
 use crate::data::channel::Channel;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ExtraData {
     Dead,
-    ProtectedByDoctor,
-    WhisperMetadata { from: u8, to: u8 },
+    /// One per night action taken, kept for the whole game to count limited uses
+    NightActionUsed,
+    WhisperMetadata {
+        from: u8,
+        to: u8,
+    },
     SaidInChannel(Channel),
 }
+
+/// The channel an entry or event with this metadata belongs to
+pub fn channel_of(extra_data: &[ExtraData]) -> Channel {
+    extra_data
+        .iter()
+        .find_map(|data| match data {
+            ExtraData::SaidInChannel(channel) => Some(channel.clone()),
+            _ => None,
+        })
+        .unwrap_or(Channel::Global)
+}
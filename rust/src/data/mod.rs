@@ -1,5 +1,6 @@
 pub mod action;
 pub mod channel;
 pub mod context_entry;
+pub mod event;
 pub mod extra_data;
 pub mod roles;
@@ -1,36 +1,207 @@
-#[derive(Debug, Clone)]
-pub enum GameRole {
-    Villager,
-    Mafioso,
-    Doctor,
-    Sheriff,
-}
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ops::Index;
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+
+/// Names a [`RoleDefinition`], which is looked up in a [`RoleBook`]. Actors and setups only store the name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GameRole(pub Cow<'static, str>);
 
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum RoleAlignment {
     Town,
     Mafia,
+    /// On nobody's side. Neutral players only win through their role's [`WinCondition`].
+    Neutral,
+}
+
+/// Everything about a role that isn't shared with the others, loaded from JSON
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoleDefinition {
+    pub name: String,
+    pub alignment: RoleAlignment,
+    pub description: String,
+    #[serde(default)]
+    pub night_action: Option<NightAction>,
+    /// Checked alongside the faction wins
+    #[serde(default)]
+    pub win_condition: Option<WinCondition>,
+    /// The alignment investigations report, overriding the setup's `neutral_killers_suspicious`
+    #[serde(default)]
+    pub appears_as: Option<RoleAlignment>,
+}
+
+/// A way for a single player to win, whatever happens to the factions
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum WinCondition {
+    /// Getting voted out during the day. The game goes on without them.
+    VotedOut,
+    /// Being the only one left alive. No faction can win while a player with this condition lives.
+    LastStanding,
+}
+
+/// What a role does on its own at night. The Mafia's kill is shared by the whole faction and isn't one of these.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NightAction {
+    pub kind: NightActionKind,
+    #[serde(default)]
+    pub targets: TargetRule,
+    /// Unlimited when unset
+    #[serde(default)]
+    pub uses: Option<u8>,
+    /// Asked when it's the role's turn. When unset, the kind's prompt is used, which can be overridden.
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+/// Night actions are picked first and then resolved together in this order,
+/// so a block lands before anything it stops and a protection before the kills it prevents
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum NightActionKind {
+    /// The target's own night action fails. The Mafia's kill fails if everyone who voted for it is blocked.
+    Block,
+    /// Saves the target from being killed tonight
+    Protect,
+    Kill,
+    /// Learns whether the target is Mafia
+    Investigate,
 }
 
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TargetRule {
+    /// Any living player but the actor
+    #[default]
+    Others,
+    /// Any living player, the actor included
+    Anyone,
+    /// Living players of another alignment
+    NotAllies,
+}
+
+static BUILTIN_ROLES: LazyLock<RoleBook> = LazyLock::new(|| {
+    serde_json::from_str(include_str!("roles.json")).expect("The built-in roles are malformed")
+});
+
 impl GameRole {
+    pub const VILLAGER: GameRole = GameRole(Cow::Borrowed("Villager"));
+    pub const MAFIOSO: GameRole = GameRole(Cow::Borrowed("Mafioso"));
+    pub const DOCTOR: GameRole = GameRole(Cow::Borrowed("Doctor"));
+    pub const SHERIFF: GameRole = GameRole(Cow::Borrowed("Sheriff"));
+
     pub fn name(&self) -> String {
-        format!("{:?}", self)
+        self.0.to_string()
+    }
+}
+
+impl RoleDefinition {
+    pub fn role(&self) -> GameRole {
+        GameRole(Cow::Owned(self.name.clone()))
+    }
+}
+
+/// The definitions roles are looked up in. Every game keeps the ones it was dealt from in its state,
+/// so a save plays the same wherever it's loaded.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RoleBook(Vec<Arc<RoleDefinition>>);
+
+/// The built-in roles
+impl Default for RoleBook {
+    fn default() -> Self {
+        BUILTIN_ROLES.clone()
+    }
+}
+
+impl RoleBook {
+    pub fn get(&self, role: &GameRole) -> Option<&RoleDefinition> {
+        self.0
+            .iter()
+            .find(|definition| definition.name == role.0)
+            .map(Arc::as_ref)
     }
 
-    pub fn alignment(&self) -> RoleAlignment {
-        match self {
-            GameRole::Mafioso => RoleAlignment::Mafia,
-            _ => RoleAlignment::Town,
+    pub fn contains(&self, role: &GameRole) -> bool {
+        self.get(role).is_some()
+    }
+
+    /// Fails with the name of the first of `roles` that isn't defined
+    pub fn check<'a>(&self, roles: impl IntoIterator<Item = &'a GameRole>) -> Result<(), String> {
+        match roles.into_iter().find(|role| !self.contains(role)) {
+            Some(role) => Err(format!("{} isn't defined", role.name())),
+            None => Ok(()),
         }
     }
 
-    pub fn description(&self) -> &str {
-        match self {
-            GameRole::Villager => "Discuss with others during the day and figure out the mafia.",
-            GameRole::Mafioso => {
-                "Discuss with fellow mafia at night to plan and vote a player to kill."
+    /// In the order they were defined, the built-in roles first
+    pub fn definitions(&self) -> impl Iterator<Item = &RoleDefinition> {
+        self.0.iter().map(Arc::as_ref)
+    }
+
+    pub fn roles(&self) -> Vec<GameRole> {
+        self.definitions().map(RoleDefinition::role).collect()
+    }
+
+    /// Adds `definitions`, replacing the roles of the same name
+    pub fn extend(&mut self, definitions: Vec<RoleDefinition>) {
+        for definition in definitions {
+            let definition = Arc::new(definition);
+            match self
+                .0
+                .iter_mut()
+                .find(|existing| existing.name == definition.name)
+            {
+                Some(existing) => *existing = definition,
+                None => self.0.push(definition),
             }
-            GameRole::Doctor => "At night, pick a person to protect from being killed.",
-            GameRole::Sheriff => "At night, investigate a player and see if they are mafia.",
         }
     }
 }
+
+/// Roles are only looked up by index once they are known to be defined, such as those of actors in a checked game
+impl Index<&GameRole> for RoleBook {
+    type Output = RoleDefinition;
+
+    fn index(&self, role: &GameRole) -> &RoleDefinition {
+        self.get(role)
+            .unwrap_or_else(|| panic!("{} isn't defined", role.name()))
+    }
+}
+
+/// Reads the roles listed in every `.json` file in `directory`, in the order of their names.
+/// A missing directory has no roles.
+pub fn load_roles(directory: &Path) -> std::io::Result<Vec<RoleDefinition>> {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    let mut definitions = Vec::new();
+    for path in paths {
+        let text = std::fs::read_to_string(&path)?;
+        let loaded: Vec<RoleDefinition> = serde_json::from_str(&text).map_err(|err| {
+            std::io::Error::other(format!("{} is malformed: {}", path.display(), err))
+        })?;
+        if let Some(unnamed) = loaded
+            .iter()
+            .position(|definition| definition.name.is_empty())
+        {
+            return Err(std::io::Error::other(format!(
+                "Role {} of {} has no name",
+                unnamed + 1,
+                path.display()
+            )));
+        }
+        definitions.extend(loaded);
+    }
+    Ok(definitions)
+}
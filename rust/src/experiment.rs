@@ -0,0 +1,192 @@
+use crate::data::event::GameEvent;
+use crate::data::roles::RoleAlignment;
+use crate::game::save::SaveFile;
+use crate::llm::tools::MultiCall;
+use crate::prompts::PromptOverrides;
+use crate::simulation::{BatchGame, SimulationConfig};
+use async_openai::types::chat::FunctionCall;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PromptVariant {
+    pub name: String,
+    /// Merged over the prompts of the lobby
+    #[serde(default)]
+    pub prompts: PromptOverrides,
+}
+
+/// One lobby played under several sets of prompts, each with the same seeds
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExperimentConfig {
+    pub lobby: SimulationConfig,
+    pub variants: Vec<PromptVariant>,
+}
+
+impl ExperimentConfig {
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&text).map_err(std::io::Error::other)?;
+        if config.variants.is_empty() {
+            return Err(std::io::Error::other(
+                "An experiment needs at least one variant",
+            ));
+        }
+        config.lobby.validate().map_err(std::io::Error::other)?;
+        Ok(config)
+    }
+
+    /// The lobby with the prompts of `variant` applied
+    pub fn lobby_for(&self, variant: &PromptVariant) -> SimulationConfig {
+        let mut lobby = self.lobby.clone();
+        lobby.prompts.merge(&variant.prompts);
+        lobby
+    }
+}
+
+/// Counts taken from the events of one or more games
+#[derive(Clone, Default, Serialize)]
+pub struct PromptMetrics {
+    pub games: u32,
+    pub mafia_wins: u32,
+    pub town_wins: u32,
+    /// Public messages and whispers
+    pub messages: u32,
+    /// Abstained discussion turns and votes
+    pub abstains: u32,
+    pub votes: u32,
+    pub decisions: u32,
+    /// Decisions without a call, with a tool that wasn't offered or with arguments that don't parse
+    pub invalid_decisions: u32,
+}
+
+impl PromptMetrics {
+    pub fn from_save(save: &SaveFile) -> Self {
+        let mut metrics = Self {
+            games: 1,
+            ..Default::default()
+        };
+        match save.final_result().and_then(|result| result.faction) {
+            Some(RoleAlignment::Mafia) => metrics.mafia_wins += 1,
+            Some(RoleAlignment::Town) => metrics.town_wins += 1,
+            Some(RoleAlignment::Neutral) | None => {}
+        }
+        for event in &save.state.events {
+            match event {
+                GameEvent::Spoke { .. } | GameEvent::Whispered { .. } => metrics.messages += 1,
+                GameEvent::Abstained { .. } => metrics.abstains += 1,
+                GameEvent::Voted { target, .. } => {
+                    metrics.votes += 1;
+                    metrics.abstains += target.is_none() as u32;
+                }
+                GameEvent::Decided {
+                    tools, tool_calls, ..
+                } => {
+                    metrics.decisions += 1;
+                    let valid = !tool_calls.is_empty()
+                        && tool_calls.iter().all(|call| is_valid_call(call, tools));
+                    metrics.invalid_decisions += !valid as u32;
+                }
+                _ => {}
+            }
+        }
+        metrics
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        self.games += other.games;
+        self.mafia_wins += other.mafia_wins;
+        self.town_wins += other.town_wins;
+        self.messages += other.messages;
+        self.abstains += other.abstains;
+        self.votes += other.votes;
+        self.decisions += other.decisions;
+        self.invalid_decisions += other.invalid_decisions;
+    }
+
+    pub fn town_win_rate(&self) -> f64 {
+        self.town_wins as f64 / self.games.max(1) as f64
+    }
+
+    pub fn messages_per_game(&self) -> f64 {
+        self.messages as f64 / self.games.max(1) as f64
+    }
+
+    /// Abstains over every turn that could have been abstained
+    pub fn abstain_rate(&self) -> f64 {
+        self.abstains as f64 / (self.messages + self.abstains + self.votes).max(1) as f64
+    }
+
+    pub fn invalid_rate(&self) -> f64 {
+        self.invalid_decisions as f64 / self.decisions.max(1) as f64
+    }
+}
+
+/// The call names an offered tool and its arguments parse.
+/// The actions of a `MultiCall` have to be valid as well.
+fn is_valid_call(call: &FunctionCall, offered: &[String]) -> bool {
+    if !offered.contains(&call.name) {
+        return false;
+    }
+    if call.name == "MultiCall" {
+        return serde_json::from_str::<MultiCall>(&call.arguments).is_ok_and(|multi| {
+            multi
+                .actions
+                .iter()
+                .all(|action| action.tool != "MultiCall" && offered.contains(&action.tool))
+        });
+    }
+    serde_json::from_str::<serde_json::Value>(&call.arguments).is_ok_and(|value| value.is_object())
+}
+
+#[derive(Serialize)]
+pub struct VariantReport {
+    pub name: String,
+    /// Games that failed have no events and are left out of the metrics
+    pub failed: u32,
+    pub metrics: PromptMetrics,
+}
+
+impl VariantReport {
+    pub fn new(name: String, games: &[BatchGame]) -> Self {
+        let mut report = Self {
+            name,
+            failed: 0,
+            metrics: PromptMetrics::default(),
+        };
+        for game in games {
+            match &game.save {
+                Some(save) => report.metrics.add(&PromptMetrics::from_save(save)),
+                None => report.failed += 1,
+            }
+        }
+        report
+    }
+}
+
+/// A Markdown table of every variant, with the difference in Town win rate to the first one
+pub fn render_reports(reports: &[VariantReport]) -> String {
+    let mut builder = String::from(
+        "| Variant | Games | Failed | Town Win % | Δ Town Win % | Mafia Win % | Messages / Game | Abstain % | Invalid Tool % |\n",
+    );
+    builder.push_str("| --- | --- | --- | --- | --- | --- | --- | --- | --- |\n");
+    let baseline = reports
+        .first()
+        .map_or(0.0, |report| report.metrics.town_win_rate());
+    for report in reports {
+        let metrics = &report.metrics;
+        builder.push_str(&format!(
+            "| {} | {} | {} | {:.1} | {:+.1} | {:.1} | {:.1} | {:.1} | {:.1} |\n",
+            report.name,
+            metrics.games,
+            report.failed,
+            100.0 * metrics.town_win_rate(),
+            100.0 * (metrics.town_win_rate() - baseline),
+            100.0 * metrics.mafia_wins as f64 / metrics.games.max(1) as f64,
+            metrics.messages_per_game(),
+            100.0 * metrics.abstain_rate(),
+            100.0 * metrics.invalid_rate(),
+        ));
+    }
+    builder
+}
@@ -0,0 +1,415 @@
+use crate::actor::ActorKind;
+use crate::data::channel::Channel;
+use crate::data::event::{ActionOutcome, DeathCause, GameEvent};
+use crate::data::roles::{GameRole, NightActionKind, RoleAlignment};
+use crate::game::EndResult;
+use crate::game::save::SaveFile;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Metrics computed from the events of a game
+#[derive(Clone, Serialize)]
+pub struct GameAnalytics {
+    pub game_id: String,
+    pub result: Option<EndResult>,
+    pub players: Vec<PlayerAnalytics>,
+    pub sheriffs: Vec<SheriffAnalytics>,
+    pub doctors: Vec<DoctorAnalytics>,
+    pub whispers: u32,
+    /// Whispers from one Mafia member to another
+    pub mafia_whispers: u32,
+    /// Every belief probe answer in order, for calibration over the game
+    pub beliefs: Vec<BeliefScore>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PlayerAnalytics {
+    pub id: u8,
+    pub name: String,
+    pub role: GameRole,
+    pub model_id: Option<String>,
+    /// Said in any channel, whispers included
+    pub messages: u32,
+    pub whispers: u32,
+    /// Abstained discussion turns and votes
+    pub abstains: u32,
+    /// Day votes that named someone
+    pub votes: u32,
+    /// Day votes of a Town player for a Mafia or neutral killing player.
+    /// `None` for everyone else, whose votes aren't scored.
+    pub accurate_votes: Option<u32>,
+    pub death: Option<Death>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Death {
+    pub is_night: bool,
+    pub number: u8,
+    pub cause: DeathCause,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SheriffAnalytics {
+    pub id: u8,
+    pub investigations: u32,
+    pub mafia_found: u32,
+    /// Found Mafia the Sheriff later called Mafia by name or ID in public
+    pub mafia_revealed: u32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DoctorAnalytics {
+    pub id: u8,
+    pub protections: u32,
+    /// Nights the Doctor's protection stopped at least one kill
+    pub saves: u32,
+}
+
+/// One answer to a belief probe, scored against the true roles
+#[derive(Clone, Serialize)]
+pub struct BeliefScore {
+    pub actor_id: u8,
+    pub model_id: String,
+    pub is_night: bool,
+    pub number: u8,
+    /// Mean squared error of the probabilities, 0 is perfect
+    pub brier: f64,
+    /// Mean probability given to players who really are Mafia
+    pub mafia_mean: Option<f64>,
+    pub town_mean: Option<f64>,
+}
+
+impl PlayerAnalytics {
+    pub fn vote_accuracy(&self) -> Option<f64> {
+        let accurate = self.accurate_votes?;
+        (self.votes > 0).then(|| accurate as f64 / self.votes as f64)
+    }
+}
+
+impl GameAnalytics {
+    pub fn from_save(save: &SaveFile) -> Self {
+        let state = &save.state;
+        let alignment = |id: u8| {
+            state
+                .get_actor_from_id(id)
+                .map(|actor| &state.role_of(actor).alignment)
+        };
+        let is_mafia = |id: u8| matches!(alignment(id), Some(RoleAlignment::Mafia));
+        // Who the Town is right to vote out
+        let is_threat = |id: u8| {
+            state
+                .get_actor_from_id(id)
+                .map(|actor| state.role_of(actor))
+                .is_some_and(|definition| match definition.alignment {
+                    RoleAlignment::Mafia => true,
+                    RoleAlignment::Neutral => definition
+                        .night_action
+                        .as_ref()
+                        .is_some_and(|action| action.kind == NightActionKind::Kill),
+                    RoleAlignment::Town => false,
+                })
+        };
+        let mut players = state
+            .actors
+            .iter()
+            .map(|actor| PlayerAnalytics {
+                id: actor.id,
+                name: actor.name.clone(),
+                role: actor.role.clone(),
+                model_id: match &actor.kind {
+                    ActorKind::Llm(llm) => Some(llm.model_id.clone()),
+                    ActorKind::Real | ActorKind::Scripted => None,
+                },
+                messages: 0,
+                whispers: 0,
+                abstains: 0,
+                votes: 0,
+                accurate_votes: (state.role_of(actor).alignment == RoleAlignment::Town)
+                    .then_some(0),
+                death: None,
+            })
+            .collect::<Vec<_>>();
+        let mut sheriffs = Vec::<SheriffAnalytics>::new();
+        let mut doctors = Vec::<DoctorAnalytics>::new();
+        let mut whispers = 0;
+        let mut mafia_whispers = 0;
+        let mut phase = (false, 0);
+        // Mafia found by a Sheriff and not revealed yet
+        let mut found = Vec::new();
+        let mut beliefs = Vec::new();
+        for event in &state.events {
+            match event {
+                GameEvent::PhaseStarted { is_night, number } => {
+                    phase = (*is_night, *number);
+                }
+                GameEvent::Spoke {
+                    actor_id,
+                    channel,
+                    message,
+                } => {
+                    if let Some(player) = players.iter_mut().find(|player| player.id == *actor_id) {
+                        player.messages += 1;
+                    }
+                    if matches!(channel, Channel::Global) {
+                        let message = message.to_lowercase();
+                        found.retain(|&(sheriff, target)| {
+                            let revealed = sheriff == *actor_id
+                                && message.contains("mafia")
+                                && state.get_actor_from_id(target).is_some_and(|target| {
+                                    message.contains(&target.name.to_lowercase())
+                                        || message.contains(&format!("id {}", target.id))
+                                });
+                            if revealed
+                                && let Some(sheriff) =
+                                    sheriffs.iter_mut().find(|entry| entry.id == sheriff)
+                            {
+                                sheriff.mafia_revealed += 1;
+                            }
+                            !revealed
+                        });
+                    }
+                }
+                GameEvent::Whispered { from, to, .. } => {
+                    whispers += 1;
+                    if is_mafia(*from) && is_mafia(*to) {
+                        mafia_whispers += 1;
+                    }
+                    if let Some(player) = players.iter_mut().find(|player| player.id == *from) {
+                        player.messages += 1;
+                        player.whispers += 1;
+                    }
+                }
+                GameEvent::Abstained { actor_id, .. } => {
+                    if let Some(player) = players.iter_mut().find(|player| player.id == *actor_id) {
+                        player.abstains += 1;
+                    }
+                }
+                GameEvent::Voted {
+                    voter,
+                    channel,
+                    target,
+                    ..
+                } => {
+                    let Some(player) = players.iter_mut().find(|player| player.id == *voter) else {
+                        continue;
+                    };
+                    match target {
+                        None => player.abstains += 1,
+                        Some(target) if matches!(channel, Channel::Global) => {
+                            player.votes += 1;
+                            if let Some(accurate) = &mut player.accurate_votes
+                                && is_threat(*target)
+                            {
+                                *accurate += 1;
+                            }
+                        }
+                        Some(_) => {}
+                    }
+                }
+                GameEvent::Investigated {
+                    sheriff,
+                    target,
+                    alignment,
+                } => {
+                    let index = match sheriffs.iter().position(|entry| entry.id == *sheriff) {
+                        Some(index) => index,
+                        None => {
+                            sheriffs.push(SheriffAnalytics {
+                                id: *sheriff,
+                                investigations: 0,
+                                mafia_found: 0,
+                                mafia_revealed: 0,
+                            });
+                            sheriffs.len() - 1
+                        }
+                    };
+                    sheriffs[index].investigations += 1;
+                    if matches!(alignment, RoleAlignment::Mafia) {
+                        sheriffs[index].mafia_found += 1;
+                        found.push((*sheriff, *target));
+                    }
+                }
+                GameEvent::Protected { doctor, .. } => {
+                    match doctors.iter_mut().find(|entry| entry.id == *doctor) {
+                        Some(entry) => entry.protections += 1,
+                        None => doctors.push(DoctorAnalytics {
+                            id: *doctor,
+                            protections: 1,
+                            saves: 0,
+                        }),
+                    }
+                }
+                GameEvent::NightResolved { actions } => {
+                    for protection in actions.iter().filter(|action| {
+                        action.kind == NightActionKind::Protect
+                            && action.outcome == ActionOutcome::Succeeded
+                    }) {
+                        let saved = actions.iter().any(|action| {
+                            action.target == protection.target
+                                && action.outcome == ActionOutcome::Prevented
+                        });
+                        if saved
+                            && let Some(doctor) = protection.actor_id
+                            && let Some(entry) = doctors.iter_mut().find(|entry| entry.id == doctor)
+                        {
+                            entry.saves += 1;
+                        }
+                    }
+                }
+                GameEvent::Killed { actor_id, cause } => {
+                    if let Some(player) = players.iter_mut().find(|player| player.id == *actor_id) {
+                        player.death = Some(Death {
+                            is_night: phase.0,
+                            number: phase.1,
+                            cause: cause.clone(),
+                        });
+                    }
+                }
+                GameEvent::BeliefsProbed {
+                    actor_id,
+                    model_id,
+                    beliefs: answers,
+                    ..
+                } if !answers.is_empty() => {
+                    let mean = |values: Vec<f64>| {
+                        (!values.is_empty())
+                            .then(|| values.iter().sum::<f64>() / values.len() as f64)
+                    };
+                    let (mafia, town): (Vec<_>, Vec<_>) =
+                        answers.iter().partition(|belief| is_mafia(belief.target));
+                    beliefs.push(BeliefScore {
+                        actor_id: *actor_id,
+                        model_id: model_id.clone(),
+                        is_night: phase.0,
+                        number: phase.1,
+                        brier: mean(
+                            answers
+                                .iter()
+                                .map(|belief| {
+                                    let truth = if is_mafia(belief.target) { 1.0 } else { 0.0 };
+                                    (belief.mafia_probability - truth).powi(2)
+                                })
+                                .collect(),
+                        )
+                        .unwrap_or_default(),
+                        mafia_mean: mean(
+                            mafia
+                                .iter()
+                                .map(|belief| belief.mafia_probability)
+                                .collect(),
+                        ),
+                        town_mean: mean(
+                            town.iter().map(|belief| belief.mafia_probability).collect(),
+                        ),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Self {
+            game_id: save.game_id.clone(),
+            result: save.final_result(),
+            players,
+            sheriffs,
+            doctors,
+            whispers,
+            mafia_whispers,
+            beliefs,
+        }
+    }
+
+    pub fn mafia_whisper_share(&self) -> Option<f64> {
+        (self.whispers > 0).then(|| self.mafia_whispers as f64 / self.whispers as f64)
+    }
+
+    fn name_of(&self, id: u8) -> String {
+        self.players
+            .iter()
+            .find(|player| player.id == id)
+            .map_or_else(|| format!("ID {}", id), |player| player.name.clone())
+    }
+
+    /// A plain-text summary for the end-of-game panel
+    pub fn render(&self) -> String {
+        let percentage = |value: Option<f64>| {
+            value.map_or(String::from("-"), |value| format!("{:.0}%", 100.0 * value))
+        };
+        let mut builder = match &self.result {
+            Some(result) => format!("{}.\n\n", result.label()),
+            None => String::from("The game did not finish.\n\n"),
+        };
+        for player in &self.players {
+            let death = match &player.death {
+                Some(death) => format!(
+                    "{} on {} {}",
+                    match death.cause {
+                        DeathCause::VotedOut => "voted out",
+                        DeathCause::Mafia => "killed",
+                        DeathCause::NightAction { .. } => "killed",
+                    },
+                    if death.is_night { "night" } else { "day" },
+                    death.number
+                ),
+                None => String::from("survived"),
+            };
+            builder.push_str(&format!(
+                "{} ({}): {} messages, {} abstains, vote accuracy {}, {}\n",
+                player.name,
+                player.role.name(),
+                player.messages,
+                player.abstains,
+                percentage(player.vote_accuracy()),
+                death
+            ));
+        }
+        builder.push('\n');
+        for sheriff in &self.sheriffs {
+            builder.push_str(&format!(
+                "Sheriff {} found Mafia in {} of {} investigations and revealed {}\n",
+                self.name_of(sheriff.id),
+                sheriff.mafia_found,
+                sheriff.investigations,
+                sheriff.mafia_revealed
+            ));
+        }
+        for doctor in &self.doctors {
+            builder.push_str(&format!(
+                "Doctor {} stopped a kill {} of {} times\n",
+                self.name_of(doctor.id),
+                doctor.saves,
+                doctor.protections
+            ));
+        }
+        builder.push_str(&format!(
+            "Whispers: {}, {} of them between Mafia members\n",
+            self.whispers,
+            percentage(self.mafia_whisper_share())
+        ));
+        for player in &self.players {
+            let scores = self
+                .beliefs
+                .iter()
+                .filter(|score| score.actor_id == player.id)
+                .map(|score| score.brier)
+                .collect::<Vec<_>>();
+            if let (Some(first), Some(last)) = (scores.first(), scores.last()) {
+                builder.push_str(&format!(
+                    "{}'s suspicions over {} polls: Brier score {:.2} at first, {:.2} at last\n",
+                    player.name,
+                    scores.len(),
+                    first,
+                    last
+                ));
+            }
+        }
+        builder
+    }
+
+    /// Writes the metrics as JSON to `directory`, named after the game
+    pub fn write(&self, directory: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(directory)?;
+        let path = directory.join(format!("{}.json", self.game_id));
+        std::fs::write(&path, serde_json::to_string_pretty(self).unwrap())?;
+        Ok(path)
+    }
+}
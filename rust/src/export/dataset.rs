@@ -0,0 +1,117 @@
+use crate::actor::ActorKind;
+use crate::data::event::GameEvent;
+use crate::data::roles::{GameRole, RoleAlignment};
+use crate::game::save::SaveFile;
+use crate::llm::tools::make_tool_by_name;
+use async_openai::types::chat::{
+    ChatCompletionMessageToolCall, ChatCompletionMessageToolCalls,
+    ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage, ChatCompletionTools,
+};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+/// Which decisions end up in the dataset. Every set field has to match.
+#[derive(Default)]
+pub struct DatasetFilter {
+    pub role: Option<GameRole>,
+    pub alignment: Option<RoleAlignment>,
+    /// Only keep decisions of the side that won the game
+    pub winners_only: bool,
+    pub model_id: Option<String>,
+}
+
+/// One line of OpenAI's chat fine-tuning format
+#[derive(Serialize)]
+pub struct FineTuningExample {
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    pub tools: Vec<ChatCompletionTools>,
+}
+
+/// Builds an example from every LLM decision in `save` that passes `filter`.
+/// Decisions without tool calls are skipped, there is nothing to learn from them.
+pub fn build_examples(save: &SaveFile, filter: &DatasetFilter) -> Vec<FineTuningExample> {
+    let mut examples = Vec::new();
+    let end_result = save.final_result();
+    for event in &save.state.events {
+        let GameEvent::Decided {
+            actor_id,
+            model_id,
+            context_len,
+            prompt,
+            tools,
+            tool_calls,
+        } = event
+        else {
+            continue;
+        };
+        let Some(actor) = save.state.get_actor_from_id(*actor_id) else {
+            continue;
+        };
+        let ActorKind::Llm(llm) = &actor.kind else {
+            continue;
+        };
+        if tool_calls.is_empty()
+            || filter.role.as_ref().is_some_and(|role| *role != actor.role)
+            || filter
+                .alignment
+                .as_ref()
+                .is_some_and(|alignment| *alignment != save.state.role_of(actor).alignment)
+            || filter.model_id.as_ref().is_some_and(|id| id != model_id)
+            || filter.winners_only
+                && !end_result
+                    .as_ref()
+                    .is_some_and(|result| result.is_win_for(actor, &save.state.roles))
+        {
+            continue;
+        }
+        let mut messages =
+            llm.build_messages(&save.state, &save.state.context[..*context_len], prompt);
+        let calls = tool_calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| {
+                ChatCompletionMessageToolCalls::Function(ChatCompletionMessageToolCall {
+                    id: format!("call_{}", index),
+                    function: call.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        messages.push(
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .tool_calls(calls)
+                .build()
+                .unwrap()
+                .into(),
+        );
+        examples.push(FineTuningExample {
+            messages,
+            tools: tools
+                .iter()
+                .filter_map(|name| make_tool_by_name(name))
+                .collect(),
+        });
+    }
+    examples
+}
+
+/// Writes the examples of every save to `path` as JSONL. Returns how many were written.
+pub fn write_dataset(
+    saves: &[SaveFile],
+    filter: &DatasetFilter,
+    path: &Path,
+) -> std::io::Result<usize> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut count = 0;
+    for save in saves {
+        for example in build_examples(save, filter) {
+            writeln!(file, "{}", serde_json::to_string(&example).unwrap())?;
+            count += 1;
+        }
+    }
+    file.flush()?;
+    Ok(count)
+}
@@ -0,0 +1,3 @@
+pub mod analytics;
+pub mod dataset;
+pub mod transcript;
@@ -0,0 +1,347 @@
+use crate::actor::{ActorKind, BaseActor};
+use crate::data::channel::Channel;
+use crate::data::context_entry::SayerType;
+use crate::data::extra_data::{ExtraData, channel_of};
+use crate::game::EndResult;
+use crate::game::save::SaveFile;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy)]
+pub enum TranscriptFormat {
+    Markdown,
+    Html,
+    Jsonl,
+}
+
+impl TranscriptFormat {
+    pub const ALL: [TranscriptFormat; 3] = [
+        TranscriptFormat::Markdown,
+        TranscriptFormat::Html,
+        TranscriptFormat::Jsonl,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "md",
+            TranscriptFormat::Html => "html",
+            TranscriptFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct TranscriptActor {
+    pub id: u8,
+    pub name: String,
+    pub role: String,
+    /// `None` for the human player
+    pub model: Option<String>,
+}
+
+impl TranscriptActor {
+    fn new(actor: &BaseActor) -> Self {
+        Self {
+            id: actor.id,
+            name: actor.name.clone(),
+            role: actor.role.name(),
+            model: match &actor.kind {
+                ActorKind::Llm(llm) => Some(llm.model_id.clone()),
+                ActorKind::Real | ActorKind::Scripted => None,
+            },
+        }
+    }
+
+    fn label(&self) -> String {
+        match &self.model {
+            Some(model) => format!("{} (ID {}, {}, {})", self.name, self.id, self.role, model),
+            None => format!("{} (ID {}, {}, Player)", self.name, self.id, self.role),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct TranscriptPhase {
+    pub is_night: bool,
+    pub number: u8,
+}
+
+impl TranscriptPhase {
+    fn title(phase: Option<TranscriptPhase>) -> String {
+        match phase {
+            Some(phase) if phase.is_night => format!("Night {}", phase.number),
+            Some(phase) => format!("Day {}", phase.number),
+            None => String::from("Setup"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct WhisperLink {
+    pub from: u8,
+    pub to: u8,
+}
+
+/// One context entry, with everything needed to read it on its own
+#[derive(Serialize)]
+pub struct TranscriptLine {
+    /// Position in the game's context
+    pub index: usize,
+    /// `None` before the first phase starts
+    pub phase: Option<TranscriptPhase>,
+    pub channel: Channel,
+    /// Set on the public notice of a whisper
+    pub whisper: Option<WhisperLink>,
+    /// `None` for the system
+    pub speaker: Option<TranscriptActor>,
+    pub content: String,
+}
+
+/// A finished or running game flattened for sharing
+pub struct Transcript {
+    pub game_id: String,
+    pub seed: u64,
+    pub actors: Vec<TranscriptActor>,
+    pub lines: Vec<TranscriptLine>,
+    pub end_result: Option<EndResult>,
+}
+
+impl Transcript {
+    /// Builds the transcript of `save`. Raw reasoning entries are only kept with `include_raw`.
+    pub fn from_save(save: &SaveFile, include_raw: bool) -> Self {
+        let actors = save
+            .state
+            .actors
+            .iter()
+            .map(TranscriptActor::new)
+            .collect::<Vec<_>>();
+        let mut checkpoints = save.checkpoints.iter().peekable();
+        let mut phase = None;
+        let mut lines = Vec::new();
+        for (index, entry) in save.state.context.iter().enumerate() {
+            while let Some(checkpoint) = checkpoints.next_if(|c| c.context_len <= index) {
+                phase = Some(TranscriptPhase {
+                    is_night: checkpoint.day_night_count.is_night,
+                    number: if checkpoint.day_night_count.is_night {
+                        checkpoint.day_night_count.night_count
+                    } else {
+                        checkpoint.day_night_count.day_count
+                    },
+                });
+            }
+            let channel = channel_of(&entry.extra_data);
+            if matches!(channel, Channel::Raw(_)) && !include_raw {
+                continue;
+            }
+            let whisper = entry.extra_data.iter().find_map(|data| match data {
+                ExtraData::WhisperMetadata { from, to } => Some(WhisperLink {
+                    from: *from,
+                    to: *to,
+                }),
+                _ => None,
+            });
+            let speaker = match entry.sayer_type {
+                SayerType::Actor(id) => actors.iter().find(|actor| actor.id == id).cloned(),
+                SayerType::System => None,
+            };
+            lines.push(TranscriptLine {
+                index,
+                phase,
+                channel,
+                whisper,
+                speaker,
+                content: entry.content.clone(),
+            });
+        }
+        Self {
+            game_id: save.game_id.clone(),
+            seed: save.seed,
+            actors,
+            lines,
+            end_result: save.final_result(),
+        }
+    }
+
+    fn actor_name(&self, id: u8) -> String {
+        self.actors
+            .iter()
+            .find(|actor| actor.id == id)
+            .map(|actor| actor.name.clone())
+            .unwrap_or_else(|| format!("ID {}", id))
+    }
+
+    fn channel_label(&self, line: &TranscriptLine) -> String {
+        if let Some(whisper) = line.whisper {
+            return format!(
+                "Whisper {} -> {}",
+                self.actor_name(whisper.from),
+                self.actor_name(whisper.to)
+            );
+        }
+        match line.channel {
+            Channel::Global => String::from("Global"),
+            Channel::Mafia => String::from("Mafia"),
+            Channel::ToSelf(id) => format!("Private to {}", self.actor_name(id)),
+            Channel::Raw(_) => String::from("Reasoning"),
+        }
+    }
+
+    fn result_label(&self) -> String {
+        self.end_result
+            .as_ref()
+            .map_or_else(|| String::from("Unfinished"), EndResult::label)
+    }
+
+    /// Splits the lines at every phase change
+    fn phases(&self) -> Vec<(String, &[TranscriptLine])> {
+        let mut phases = Vec::new();
+        let mut start = 0;
+        for index in 1..=self.lines.len() {
+            let title = TranscriptPhase::title(self.lines[start].phase);
+            if index == self.lines.len() || TranscriptPhase::title(self.lines[index].phase) != title
+            {
+                phases.push((title, &self.lines[start..index]));
+                start = index;
+            }
+        }
+        phases
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut builder = format!("# Game {}\n\n", self.game_id);
+        builder.push_str(&format!(
+            "Seed: {}  \nResult: {}\n\n",
+            self.seed,
+            self.result_label()
+        ));
+        builder.push_str("| ID | Name | Role | Model |\n| --- | --- | --- | --- |\n");
+        for actor in &self.actors {
+            builder.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                actor.id,
+                actor.name,
+                actor.role,
+                actor.model.as_deref().unwrap_or("Player")
+            ));
+        }
+        for (title, lines) in self.phases() {
+            builder.push_str(&format!("\n## {}\n", title));
+            for line in lines {
+                let speaker = line
+                    .speaker
+                    .as_ref()
+                    .map(TranscriptActor::label)
+                    .unwrap_or_else(|| String::from("System"));
+                builder.push_str(&format!(
+                    "\n**{}** _[{}]_\n\n",
+                    speaker,
+                    self.channel_label(line)
+                ));
+                for text in line.content.trim().lines() {
+                    builder.push_str(&format!("> {}\n", text));
+                }
+            }
+        }
+        builder
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut builder =
+            String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        builder.push_str(&format!(
+            "<title>Game {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+            escape_html(&self.game_id),
+            HTML_STYLE
+        ));
+        builder.push_str(&format!(
+            "<h1>Game {}</h1>\n<p>Seed: {}<br>Result: {}</p>\n",
+            escape_html(&self.game_id),
+            self.seed,
+            self.result_label()
+        ));
+        builder.push_str("<table>\n<tr><th>ID</th><th>Name</th><th>Role</th><th>Model</th></tr>\n");
+        for actor in &self.actors {
+            builder.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                actor.id,
+                escape_html(&actor.name),
+                escape_html(&actor.role),
+                escape_html(actor.model.as_deref().unwrap_or("Player"))
+            ));
+        }
+        builder.push_str("</table>\n");
+        for (title, lines) in self.phases() {
+            builder.push_str(&format!("<h2>{}</h2>\n", title));
+            for line in lines {
+                let class = if line.whisper.is_some() {
+                    "whisper"
+                } else {
+                    match line.channel {
+                        Channel::Global => "global",
+                        Channel::Mafia => "mafia",
+                        Channel::ToSelf(_) => "private",
+                        Channel::Raw(_) => "raw",
+                    }
+                };
+                let speaker = line
+                    .speaker
+                    .as_ref()
+                    .map(TranscriptActor::label)
+                    .unwrap_or_else(|| String::from("System"));
+                builder.push_str(&format!(
+                    "<div class=\"entry {}\"><div class=\"sayer\">{} <span class=\"channel\">[{}]</span></div><div class=\"content\">{}</div></div>\n",
+                    class,
+                    escape_html(&speaker),
+                    escape_html(&self.channel_label(line)),
+                    escape_html(line.content.trim())
+                ));
+            }
+        }
+        builder.push_str("</body>\n</html>\n");
+        builder
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        let mut builder = String::new();
+        for line in &self.lines {
+            builder.push_str(&serde_json::to_string(line).unwrap());
+            builder.push('\n');
+        }
+        builder
+    }
+
+    pub fn render(&self, format: TranscriptFormat) -> String {
+        match format {
+            TranscriptFormat::Markdown => self.to_markdown(),
+            TranscriptFormat::Html => self.to_html(),
+            TranscriptFormat::Jsonl => self.to_jsonl(),
+        }
+    }
+
+    /// Writes every format to `directory`, named after the game. Returns the written paths.
+    pub fn write_all(&self, directory: &Path) -> std::io::Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(directory)?;
+        let mut paths = Vec::new();
+        for format in TranscriptFormat::ALL {
+            let path = directory.join(format!("{}.{}", self.game_id, format.extension()));
+            std::fs::File::create(&path)?.write_all(self.render(format).as_bytes())?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;max-width:50em;margin:auto;padding:1em;background:#1e1e24;color:#e6e6e6}\
+table{border-collapse:collapse}td,th{border:1px solid #555;padding:.2em .6em}\
+.entry{margin:.5em 0;padding:.4em .6em;border-left:4px solid #888;background:#2a2a32}\
+.sayer{font-weight:bold}.channel{font-weight:normal;color:#aaa}.content{white-space:pre-wrap}\
+.mafia{border-color:#c0392b}.whisper{border-color:#8e44ad}.private{border-color:#2980b9}\
+.raw{border-color:#555;color:#aaa;font-size:.9em}";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,94 @@
+use crate::actor::parse_real_input;
+use crate::data::action::Action;
+use crate::data::channel::Channel;
+use crate::data::context_entry::{ContextEntry, SayerType};
+use crate::data::extra_data::ExtraData;
+use crate::frontend::{GameFrontend, PromptFuture, Recovery, RecoveryFuture, TurnFailure};
+use crate::game::GameState;
+
+/// Shows nothing and makes any playable actor abstain, for scripted runs
+pub struct NullFrontend;
+
+impl GameFrontend for NullFrontend {
+    fn prompt_player(&self, _actor_id: u8, _prompt: &str) -> PromptFuture<'_> {
+        Box::pin(async { Action::Abstain })
+    }
+}
+
+/// Prints the public conversation to stdout and reads the playable actor's commands from stdin
+pub struct TerminalFrontend;
+
+impl GameFrontend for TerminalFrontend {
+    fn context_added(&self, state: &GameState, entry: &ContextEntry) {
+        let public = entry.extra_data.iter().all(|data| {
+            matches!(
+                data,
+                ExtraData::SaidInChannel(Channel::Global) | ExtraData::WhisperMetadata { .. }
+            )
+        });
+        if !public {
+            return;
+        }
+        match entry.sayer_type {
+            SayerType::Actor(id) => match state.get_actor_from_id(id) {
+                Some(actor) => println!("{} (ID {}): {}", actor.name, actor.id, entry.content),
+                None => println!("ID {}: {}", id, entry.content),
+            },
+            SayerType::System => println!("System: {}", entry.content),
+        }
+    }
+
+    fn prompt_player(&self, _actor_id: u8, prompt: &str) -> PromptFuture<'_> {
+        let prompt = prompt.to_string();
+        Box::pin(async move {
+            loop {
+                println!("System: {}", prompt);
+                let line = tokio::task::spawn_blocking(|| {
+                    let mut line = String::new();
+                    std::io::stdin().read_line(&mut line).map(|_| line)
+                })
+                .await
+                .unwrap();
+                match line {
+                    Ok(line) if line.is_empty() => return Action::Abstain,
+                    Ok(line) => {
+                        if let Some(action) = parse_real_input(&line) {
+                            return action;
+                        }
+                    }
+                    Err(_) => return Action::Abstain,
+                }
+            }
+        })
+    }
+
+    fn turn_failed(&self, failure: &TurnFailure) -> RecoveryFuture<'_> {
+        let failure = failure.to_string();
+        Box::pin(async move {
+            loop {
+                println!("{}", failure);
+                println!("retry, skip, model <id> or abort?");
+                let line = tokio::task::spawn_blocking(|| {
+                    let mut line = String::new();
+                    std::io::stdin().read_line(&mut line).map(|_| line)
+                })
+                .await
+                .unwrap();
+                let Ok(line) = line else {
+                    return Recovery::Abort;
+                };
+                match line.trim().split_once(' ') {
+                    Some(("model", model_id)) if !model_id.trim().is_empty() => {
+                        return Recovery::ReplaceModel(model_id.trim().to_string());
+                    }
+                    _ => match line.trim() {
+                        "retry" => return Recovery::Retry,
+                        "skip" => return Recovery::Skip,
+                        "abort" | "" => return Recovery::Abort,
+                        _ => {}
+                    },
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,76 @@
+use crate::data::action::Action;
+use crate::data::context_entry::ContextEntry;
+use crate::game::GameState;
+use crate::game::save::SaveFile;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+
+pub mod headless;
+
+pub type PromptFuture<'a> = Pin<Box<dyn Future<Output = Action> + Send + 'a>>;
+pub type RecoveryFuture<'a> = Pin<Box<dyn Future<Output = Recovery> + Send + 'a>>;
+
+/// A turn that could not be turned into an action
+pub struct TurnFailure {
+    pub actor_id: u8,
+    pub name: String,
+    /// `None` for actors that aren't LLMs
+    pub model_id: Option<String>,
+    pub cause: String,
+}
+
+impl Display for TurnFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.model_id {
+            Some(model_id) => write!(
+                f,
+                "The turn of {} (ID {}, {}) failed: {}",
+                self.name, self.actor_id, model_id, self.cause
+            ),
+            None => write!(
+                f,
+                "The turn of {} (ID {}) failed: {}",
+                self.name, self.actor_id, self.cause
+            ),
+        }
+    }
+}
+
+/// What to do about a [`TurnFailure`]
+pub enum Recovery {
+    /// Prompt the actor again
+    Retry,
+    /// Continue as if the actor abstained
+    Skip,
+    /// Switch the actor to another model for the rest of the game, then retry
+    ReplaceModel(String),
+    /// Give up on the game. It is marked as [`crate::game::Game::aborted`] and the game loops stop.
+    Abort,
+}
+
+/// Everything the game loop needs from whatever is presenting it.
+/// The Godot `Chat` node is one implementation, the headless ones live in [`headless`].
+pub trait GameFrontend: Send + Sync {
+    fn actors_initialized(&self, _state: &GameState) {}
+
+    fn context_added(&self, _state: &GameState, _entry: &ContextEntry) {}
+
+    fn actor_list_changed(&self, _state: &GameState) {}
+
+    fn focus_actor(&self, _actor_id: u8, _content: String) {}
+
+    /// A save of the game as of the start of the current phase
+    fn checkpoint(&self, _save: &SaveFile) {}
+
+    /// A save of the finished game, sent once its end has been announced
+    fn game_ended(&self, _save: &SaveFile) {}
+
+    /// Asks the human behind `ActorKind::Real` for an action
+    fn prompt_player(&self, actor_id: u8, prompt: &str) -> PromptFuture<'_>;
+
+    /// Decides how the game goes on after a failed turn. By default the game is given up on.
+    fn turn_failed(&self, _failure: &TurnFailure) -> RecoveryFuture<'_> {
+        Box::pin(async { Recovery::Abort })
+    }
+}
@@ -1,34 +1,20 @@
 use crate::actor::BaseActor;
+use crate::data::action::Action;
 use crate::data::context_entry::ContextEntry;
-use crate::game::{ACTORS, CONTEXT, Game};
+use crate::data::roles::RoleDefinition;
+use crate::game::{Game, GameState};
 
-#[allow(static_mut_refs)]
 impl Game {
-    pub fn get_actors() -> &'static Vec<BaseActor> {
-        unsafe { &ACTORS }
-    }
-
-    pub fn get_actors_mut() -> &'static mut Vec<BaseActor> {
-        unsafe { &mut ACTORS }
-    }
-
-    pub fn get_context() -> &'static Vec<ContextEntry> {
-        unsafe { &CONTEXT }
-    }
-
-    pub fn get_context_mut() -> &'static mut Vec<ContextEntry> {
-        unsafe { &mut CONTEXT }
-    }
-
     pub fn add_to_context(&mut self, entry: ContextEntry) {
-        Self::get_context_mut().push(entry);
-        self.command_sender
-            .send(crate::chat::ChatCommand::RefreshContextWithActor)
-            .unwrap();
+        self.state.context.push(entry);
+        self.frontend
+            .context_added(&self.state, self.state.context.last().unwrap());
     }
+}
 
-    pub fn get_nondead_actors() -> Vec<&'static BaseActor> {
-        Self::get_actors()
+impl GameState {
+    pub fn get_nondead_actors(&self) -> Vec<&BaseActor> {
+        self.actors
             .iter()
             .filter(|actor| {
                 !actor
@@ -39,7 +25,34 @@ impl Game {
             .collect()
     }
 
-    pub fn get_actor_from_id(id: u8) -> Option<&'static BaseActor> {
-        Self::get_actors().iter().find(|actor| actor.id == id)
+    pub fn get_nondead_ids(&self) -> Vec<u8> {
+        self.get_nondead_actors()
+            .iter()
+            .map(|actor| actor.id)
+            .collect()
+    }
+
+    pub fn get_actor_from_id(&self, id: u8) -> Option<&BaseActor> {
+        self.actors.iter().find(|actor| actor.id == id)
+    }
+
+    pub fn get_actor_from_id_mut(&mut self, id: u8) -> Option<&mut BaseActor> {
+        self.actors.iter_mut().find(|actor| actor.id == id)
+    }
+
+    /// The definition of the actor's role, which every actor of a dealt or loaded game has
+    pub fn role_of(&self, actor: &BaseActor) -> &RoleDefinition {
+        &self.roles[&actor.role]
+    }
+
+    /// The first ID in `action` that belongs to no actor
+    pub fn unknown_id(&self, action: &Action) -> Option<u8> {
+        match action {
+            Action::Whisper(id, _) | Action::TagPlayerForComment(id) | Action::ProvideID(id) => {
+                self.get_actor_from_id(*id).is_none().then_some(*id)
+            }
+            Action::MultiCall(actions) => actions.iter().find_map(|action| self.unknown_id(action)),
+            Action::Abstain | Action::Talk(_) => None,
+        }
     }
 }
@@ -0,0 +1,66 @@
+use crate::game::save::{SaveError, SaveFile};
+use std::path::{Path, PathBuf};
+
+/// Rolling snapshots of the game, written at every phase boundary.
+/// Only the newest [`Autosave::keep`] files in the directory are kept, whichever game they belong to.
+#[derive(Clone)]
+pub struct Autosave {
+    pub directory: PathBuf,
+    pub keep: usize,
+}
+
+impl Autosave {
+    pub const DEFAULT_KEEP: usize = 5;
+
+    pub fn new(directory: &Path) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            keep: Self::DEFAULT_KEEP,
+        }
+    }
+
+    pub fn write(&self, save: &SaveFile) -> Result<PathBuf, SaveError> {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        // Zero-padded so the names sort by time
+        let path = self
+            .directory
+            .join(format!("autosave-{:013}-{}.json", millis, save.game_id));
+        save.write(&path)?;
+        for old in self.files().iter().rev().skip(self.keep.max(1)) {
+            if let Err(err) = std::fs::remove_file(old) {
+                tracing::warn!("Failed to remove {}: {}", old.display(), err);
+            }
+        }
+        Ok(path)
+    }
+
+    /// Autosaves from oldest to newest
+    fn files(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        let mut files = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("autosave-") && name.ends_with(".json"))
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    /// The newest autosave that can be read, if its game hasn't ended
+    pub fn latest_unfinished(&self) -> Option<SaveFile> {
+        let save = self
+            .files()
+            .iter()
+            .rev()
+            .find_map(|path| SaveFile::read(path).ok())?;
+        save.final_result().is_none().then_some(save)
+    }
+}
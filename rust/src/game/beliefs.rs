@@ -0,0 +1,57 @@
+use crate::actor::ActorKind;
+use crate::data::event::{Belief, GameEvent};
+use crate::game::Game;
+use crate::prompts::general::belief_probe;
+use serde::{Deserialize, Serialize};
+
+/// A point in the game at which [`Game::belief_probes`] can poll the LLM actors
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ProbePoint {
+    /// After the day's vote
+    DayEnd,
+    /// After the Mafia picked their target
+    NightEnd,
+}
+
+impl Game {
+    /// Privately asks every living LLM actor how likely each other living player is to be Mafia,
+    /// if `point` is one of [`Game::belief_probes`]. The answers are only kept as events.
+    pub async fn probe_beliefs(&mut self, point: ProbePoint) {
+        if !self.belief_probes.contains(&point) {
+            return;
+        }
+        let living = self.state.get_nondead_ids();
+        for actor_id in living.iter().copied() {
+            let actor = self.state.get_actor_from_id(actor_id).unwrap();
+            let ActorKind::Llm(llm) = &actor.kind else {
+                continue;
+            };
+            let others = living
+                .iter()
+                .filter(|id| **id != actor_id)
+                .filter_map(|id| self.state.get_actor_from_id(*id))
+                .collect::<Vec<_>>();
+            let prompt = belief_probe(&self.prompts, &others);
+            let Some(report) = llm.probe_beliefs(&prompt, self).await else {
+                continue;
+            };
+            let model_id = llm.model_id.clone();
+            let beliefs = report
+                .suspicions
+                .into_iter()
+                .filter(|suspicion| suspicion.id != actor_id && living.contains(&suspicion.id))
+                .map(|suspicion| Belief {
+                    target: suspicion.id,
+                    mafia_probability: suspicion.mafia_probability.clamp(0.0, 1.0),
+                })
+                .collect();
+            self.emit(GameEvent::BeliefsProbed {
+                actor_id,
+                model_id,
+                point,
+                beliefs,
+                reasoning: report.reasoning,
+            });
+        }
+    }
+}
@@ -1,11 +1,10 @@
-use crate::actor::BaseActor;
-use crate::chat::ChatCommand;
 use crate::data::action::Action;
 use crate::data::channel::Channel;
 use crate::data::context_entry::{ContextEntry, SayerType};
-use crate::data::extra_data::ExtraData;
+use crate::data::event::GameEvent;
+use crate::data::extra_data::{ExtraData, channel_of};
 use crate::game::Game;
-use crate::llm::tools::Tool;
+use crate::llm::tools::discussion_tools;
 use crate::prompts::general::{
     abstained_in_discussion, public_whisper_notice, tagged_for_comment, whispered, whisperer,
     your_turn_to_talk,
@@ -15,38 +14,31 @@ use std::collections::VecDeque;
 impl Game {
     pub async fn run_discussion(
         &mut self,
-        actors: &[&BaseActor],
+        actors: &[u8],
         mut core_messages: u8,
         mut extra_messages: u8,
         mut extra_data: Vec<ExtraData>,
     ) {
-        let mut turn_queue: VecDeque<u8> = actors.iter().map(|actor| actor.id).collect();
+        let mut turn_queue: VecDeque<u8> = actors.iter().copied().collect();
 
         let mut used_message = false;
 
+        // Abstains don't use up messages, so an aborted game would talk forever
         while let Some(actor_id) = turn_queue.pop_front()
             && core_messages > 0
+            && self.aborted.is_none()
         {
             if turn_queue.is_empty() && core_messages > 0 {
-                actors
-                    .iter()
-                    .map(|actor| actor.id)
-                    .for_each(|item| turn_queue.push_back(item));
+                actors.iter().for_each(|&item| turn_queue.push_back(item));
             }
 
-            let actor = Self::get_actor_from_id(actor_id).unwrap();
+            let actor = self.state.get_actor_from_id(actor_id).unwrap();
 
-            let action = actor
-                .prompt(
-                    &your_turn_to_talk(actor, core_messages, extra_messages),
-                    self,
-                    &[
-                        crate::llm::tools::Abstain::make_tool(),
-                        crate::llm::tools::Talk::make_tool(),
-                        crate::llm::tools::TagPlayerForComment::make_tool(),
-                        crate::llm::tools::Whisper::make_tool(),
-                        crate::llm::tools::MultiCall::make_tool(),
-                    ],
+            let action = self
+                .prompt_actor(
+                    actor_id,
+                    &your_turn_to_talk(&self.prompts, actor, core_messages, extra_messages),
+                    &discussion_tools(),
                 )
                 .await;
 
@@ -58,11 +50,13 @@ impl Game {
                 &mut extra_data,
             );
 
-            godot::global::godot_print!(
-                "Turn: {} (Before handling action) Core messages: {} Extra messages: {}",
-                actor.name,
+            let name = self.state.get_actor_from_id(actor_id).unwrap().name.clone();
+
+            tracing::debug!(
+                actor = %name,
                 core_messages,
-                extra_messages
+                extra_messages,
+                "Before handling the action"
             );
 
             if used_message {
@@ -73,11 +67,11 @@ impl Game {
                 }
             }
 
-            godot::global::godot_print!(
-                "Turn: {} (After handling action) Core messages: {} Extra messages: {}",
-                actor.name,
+            tracing::debug!(
+                actor = %name,
                 core_messages,
-                extra_messages
+                extra_messages,
+                "After handling the action"
             );
         }
     }
@@ -100,12 +94,8 @@ impl Game {
             &mut final_content,
         );
         if !final_content.is_empty() {
-            self.command_sender
-                .send(ChatCommand::CameraFocus(
-                    actor_id,
-                    final_content.trim().to_string(),
-                ))
-                .unwrap();
+            self.frontend
+                .focus_actor(actor_id, final_content.trim().to_string());
         }
     }
 
@@ -125,50 +115,78 @@ impl Game {
                     sayer_type: SayerType::Actor(actor_id),
                     extra_data: extra_data.clone(),
                 });
+                self.emit(GameEvent::Spoke {
+                    actor_id,
+                    channel: channel_of(extra_data),
+                    message: content.clone(),
+                });
                 *used_message = true;
                 final_content.push_str(&format!("{}\n", &content));
             }
             Action::Abstain => {
                 self.add_to_context(ContextEntry {
-                    content: abstained_in_discussion(Self::get_actor_from_id(actor_id).unwrap()),
+                    content: abstained_in_discussion(
+                        self.state.get_actor_from_id(actor_id).unwrap(),
+                    ),
                     sayer_type: SayerType::System,
                     extra_data: extra_data.clone(),
                 });
+                self.emit(GameEvent::Abstained {
+                    actor_id,
+                    channel: channel_of(extra_data),
+                });
                 final_content.push_str("*Abstained*\n");
             }
             Action::Whisper(to, message) => {
                 let mut extra_data = extra_data.clone();
                 extra_data.push(ExtraData::WhisperMetadata { from: actor_id, to });
-                let from = Self::get_actor_from_id(actor_id).unwrap();
-                let target = Self::get_actor_from_id(to).unwrap();
+                let from = self.state.get_actor_from_id(actor_id).unwrap();
+                let target = self.state.get_actor_from_id(to).unwrap();
+                let notice = public_whisper_notice(from, target);
+                let to_whisperer = whisperer(target, &message);
+                let to_whispered = whispered(from, &message);
+                let target_name = target.name.clone();
                 self.add_to_context(ContextEntry {
-                    content: public_whisper_notice(from, Self::get_actor_from_id(to).unwrap()),
+                    content: notice,
                     sayer_type: SayerType::System,
                     extra_data,
                 });
                 self.add_to_context(ContextEntry {
-                    content: whisperer(target, &message),
+                    content: to_whisperer,
                     sayer_type: SayerType::System,
                     extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(actor_id))],
                 });
                 self.add_to_context(ContextEntry {
-                    content: whispered(from, &message),
+                    content: to_whispered,
                     sayer_type: SayerType::System,
                     extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(to))],
                 });
+                self.emit(GameEvent::Whispered {
+                    from: actor_id,
+                    to,
+                    message,
+                });
                 *used_message = true;
-                final_content.push_str(&format!("*Whispered to {}*\n", target.name));
+                final_content.push_str(&format!("*Whispered to {}*\n", target_name));
             }
             Action::TagPlayerForComment(target_id) => {
-                let target = Self::get_actor_from_id(target_id).unwrap();
+                let target = self.state.get_actor_from_id(target_id).unwrap();
+                let target_name = target.name.clone();
                 self.add_to_context(ContextEntry {
-                    content: tagged_for_comment(Self::get_actor_from_id(actor_id).unwrap(), target),
+                    content: tagged_for_comment(
+                        self.state.get_actor_from_id(actor_id).unwrap(),
+                        target,
+                    ),
                     sayer_type: SayerType::System,
                     extra_data: extra_data.clone(),
                 });
+                self.emit(GameEvent::Tagged {
+                    actor_id,
+                    target: target_id,
+                });
                 turn_queue.push_front(target_id);
                 *used_message = true;
-                final_content.push_str(&format!("*Tagged {}*\n", target.name));
+                final_content.push_str(&format!("*Tagged {}*\n", target_name));
             }
             Action::MultiCall(actions) => {
                 for sub_action in actions {
@@ -183,9 +201,9 @@ impl Game {
                 }
             }
             Action::ProvideID(_) => {
-                godot::global::godot_warn!(
+                tracing::warn!(
                     "{} attempted to use ProvideID in a discussion",
-                    Self::get_actor_from_id(actor_id).unwrap().name
+                    self.state.get_actor_from_id(actor_id).unwrap().name
                 );
             }
         }
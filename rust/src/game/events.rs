@@ -0,0 +1,50 @@
+use crate::data::event::GameEvent;
+use crate::game::{Game, GameState};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Receives every [`GameEvent`] right after it is recorded in [`GameState::events`]
+pub trait EventSubscriber: Send + Sync {
+    fn event_emitted(&self, state: &GameState, event: &GameEvent);
+}
+
+/// Appends every event to a file as a line of JSON
+pub struct EventLog {
+    file: Mutex<std::fs::File>,
+}
+
+impl EventLog {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            file: Mutex::new(std::fs::File::create(path)?),
+        })
+    }
+}
+
+impl EventSubscriber for EventLog {
+    fn event_emitted(&self, _state: &GameState, event: &GameEvent) {
+        let line = serde_json::to_string(event).unwrap();
+        if let Err(err) = writeln!(self.file.lock().unwrap(), "{}", line) {
+            tracing::warn!("Failed to write to the event log: {}", err);
+        }
+    }
+}
+
+impl Game {
+    pub fn subscribe(&mut self, subscriber: Box<dyn EventSubscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn emit(&mut self, event: GameEvent) {
+        tracing::debug!(event = %serde_json::to_string(&event).unwrap(), "Event");
+        self.state.events.push(event);
+        let event = self.state.events.last().unwrap();
+        for subscriber in &self.subscribers {
+            subscriber.event_emitted(&self.state, event);
+        }
+    }
+}
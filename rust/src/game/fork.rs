@@ -0,0 +1,184 @@
+use crate::actor::ActorKind;
+use crate::game::GameState;
+use crate::game::save::{ParentLink, SAVE_VERSION, SaveError, SaveFile, new_game_id};
+use async_openai::types::chat::ReasoningEffort;
+
+/// Changes applied to one LLM actor of a fork, `None` keeps what the parent had
+#[derive(Clone, Default)]
+pub struct ActorOverride {
+    pub actor_id: u8,
+    pub model_id: Option<String>,
+    pub instructions: Option<String>,
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+impl SaveFile {
+    /// Branches the game off at `context_index`. Games resume at phase boundaries,
+    /// so the branch starts from the beginning of the phase containing that entry,
+    /// or from the first phase for the introduction before it.
+    pub fn fork(
+        &self,
+        context_index: usize,
+        overrides: &[ActorOverride],
+    ) -> Result<SaveFile, SaveError> {
+        let parent = Some(ParentLink {
+            game_id: self.game_id.clone(),
+            context_index,
+        });
+        // A save from before the first phase has nowhere to branch from
+        if self.checkpoints.is_empty() {
+            return Err(SaveError::NoPhaseHistory);
+        }
+        let index = self
+            .checkpoints
+            .iter()
+            .rposition(|checkpoint| checkpoint.context_len <= context_index)
+            .unwrap_or(0);
+        let checkpoint = &self.checkpoints[index];
+        let mut fork = SaveFile {
+            version: SAVE_VERSION,
+            game_id: new_game_id(),
+            parent,
+            seed: self.seed,
+            rng: checkpoint.rng.clone(),
+            playable_actor: self.playable_actor,
+            last_kill: checkpoint.last_kill.clone(),
+            day_night_count: checkpoint.day_night_count.clone(),
+            end_result: None,
+            state: GameState {
+                actors: checkpoint.actors.clone(),
+                context: self.state.context[..checkpoint.context_len].to_vec(),
+                events: self.state.events[..checkpoint.events_len].to_vec(),
+                roles: self.state.roles.clone(),
+            },
+            checkpoints: self.checkpoints[..index].to_vec(),
+            prompts: self.prompts.clone(),
+            setup: self.setup.clone(),
+            belief_probes: self.belief_probes.clone(),
+        };
+        for change in overrides {
+            let Some(actor) = fork.state.get_actor_from_id_mut(change.actor_id) else {
+                continue;
+            };
+            if let ActorKind::Llm(llm) = &mut actor.kind {
+                if let Some(model_id) = &change.model_id {
+                    llm.model_id = model_id.clone();
+                }
+                if let Some(instructions) = &change.instructions {
+                    llm.instructions = Some(instructions.clone());
+                }
+                if let Some(reasoning_effort) = &change.reasoning_effort {
+                    llm.reasoning_effort = Some(reasoning_effort.clone());
+                }
+            }
+        }
+        Ok(fork)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ActorOverride;
+    use crate::actor::ActorKind;
+    use crate::data::channel::Channel;
+    use crate::data::context_entry::{ContextEntry, SayerType};
+    use crate::data::extra_data::ExtraData;
+    use crate::data::roles::GameRole;
+    use crate::game::Game;
+    use crate::game::night::tests::game_with;
+    use crate::game::save::SaveFile;
+    use crate::llm::ai_interface::AIInterface;
+
+    fn say(game: &mut Game, content: &str) {
+        game.add_to_context(ContextEntry {
+            content: content.to_string(),
+            sayer_type: SayerType::System,
+            extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
+        });
+    }
+
+    fn model_of(save: &SaveFile, id: u8) -> &AIInterface {
+        match &save.state.get_actor_from_id(id).unwrap().kind {
+            ActorKind::Llm(llm) => llm,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Two introduction entries, a phase of three entries during which player 3 dies, then two more
+    fn played() -> SaveFile {
+        let mut game = game_with(&[
+            GameRole::MAFIOSO,
+            GameRole::DOCTOR,
+            GameRole::VILLAGER,
+            GameRole::VILLAGER,
+        ]);
+        for actor in &mut game.state.actors {
+            actor.kind = ActorKind::Llm(AIInterface {
+                model_id: format!("model-{}", actor.id),
+                owner_id: actor.id,
+                instructions: Some(String::from("Play well")),
+                reasoning_effort: None,
+            });
+        }
+        say(&mut game, "intro 0");
+        say(&mut game, "intro 1");
+        game.checkpoint();
+        say(&mut game, "day 2");
+        say(&mut game, "day 3");
+        say(&mut game, "day 4");
+        game.state
+            .get_actor_from_id_mut(3)
+            .unwrap()
+            .extra_data
+            .push(ExtraData::Dead);
+        game.checkpoint();
+        say(&mut game, "night 5");
+        say(&mut game, "night 6");
+        game.to_save()
+    }
+
+    #[test]
+    fn snaps_to_the_start_of_the_phase() {
+        let save = played();
+        let fork = save.fork(6, &[]).unwrap();
+        assert_eq!(fork.state.context.len(), 5);
+        assert_eq!(fork.checkpoints.len(), 1);
+        assert_eq!(fork.state.get_nondead_ids(), vec![0, 1, 2]);
+        assert_eq!(fork.parent.as_ref().unwrap().game_id, save.game_id);
+        assert_eq!(fork.parent.as_ref().unwrap().context_index, 6);
+        assert_ne!(fork.game_id, save.game_id);
+
+        let fork = save.fork(3, &[]).unwrap();
+        assert_eq!(fork.state.context.len(), 2);
+        assert!(fork.checkpoints.is_empty());
+        assert_eq!(fork.state.get_nondead_ids(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn introduction_forks_from_the_first_phase() {
+        let fork = played().fork(0, &[]).unwrap();
+        assert_eq!(fork.state.context.len(), 2);
+    }
+
+    #[test]
+    fn overrides_only_touch_their_actor() {
+        let fork = played()
+            .fork(
+                6,
+                &[ActorOverride {
+                    actor_id: 1,
+                    model_id: Some(String::from("swapped")),
+                    ..Default::default()
+                }],
+            )
+            .unwrap();
+        assert_eq!(model_of(&fork, 1).model_id, "swapped");
+        assert_eq!(
+            model_of(&fork, 1).instructions.as_deref(),
+            Some("Play well")
+        );
+        for id in [0, 2, 3] {
+            assert_eq!(model_of(&fork, id).model_id, format!("model-{}", id));
+        }
+    }
+}
@@ -1,94 +1,88 @@
 use crate::actor::{ActorKind, BaseActor};
-use crate::chat::Chat;
 use crate::data::channel::Channel;
 use crate::data::context_entry::{ContextEntry, SayerType};
 use crate::data::extra_data::ExtraData;
-use crate::game::{EXTRA_MESSAGES, Game};
+use crate::data::roles::GameRole;
+use crate::game::Game;
 use crate::prompts::general::{build_actor_list, build_role_list, introduce_you, utter_beginning};
 use crate::prompts::specific::mafia::build_mafia_list;
-use godot::obj::WithBaseField;
 use rand::seq::SliceRandom;
 
 impl Game {
-    pub fn before_init(&self, chat: &mut Chat) {
-        #[cfg(feature = "development")]
-        chat.setup_developer_window();
-        chat.setup_menu();
-    }
-
-    pub fn init_actors(&mut self, actors: Vec<BaseActor>, chat: &mut Chat) {
-        Self::get_actors_mut().extend(actors);
-        Self::get_actors_mut().shuffle(&mut rand::rng());
-        let mut role_pool = Vec::new();
-        for _ in 0..3 {
-            role_pool.push(crate::data::roles::GameRole::Mafioso);
-        }
-        role_pool.push(crate::data::roles::GameRole::Doctor);
-        role_pool.push(crate::data::roles::GameRole::Sheriff);
-        for (index, role) in role_pool
-            .iter()
-            .enumerate()
-            .take(std::cmp::min(Self::get_actors().len(), role_pool.len()))
-        {
-            Self::get_actors_mut()[index].role = role.clone();
+    /// Actors that come in with a role other than `Villager` keep it, the rest are dealt from the setup.
+    /// Roles are looked up in `state.roles`, which has to define the ones the actors come in with.
+    pub fn init_actors(&mut self, actors: Vec<BaseActor>) {
+        self.state.actors.extend(actors);
+        tracing::info!(seed = self.seed, "Dealing roles");
+        self.state.actors.shuffle(&mut self.rng);
+        let mut role_pool =
+            self.setup
+                .deal(self.state.actors.len(), &self.state.roles, &mut self.rng);
+        for actor in &self.state.actors {
+            if let Some(index) = role_pool.iter().position(|role| *role == actor.role) {
+                role_pool.remove(index);
+            }
         }
-        Self::get_actors_mut().sort_by(|a, b| a.id.cmp(&b.id));
-        chat.spawn_visuals(Self::get_actors());
-        if Self::get_actors()
-            .iter()
-            .any(|actor| matches!(actor.kind, ActorKind::Real))
+        for (actor, role) in self
+            .state
+            .actors
+            .iter_mut()
+            .filter(|actor| actor.role == GameRole::VILLAGER)
+            .zip(role_pool)
         {
-            self.command_sender
-                .send(crate::chat::ChatCommand::Closure(Box::new(|chat| {
-                    chat.base()
-                        .get_node_as::<godot::classes::PanelContainer>("Controls BG")
-                        .show();
-                })))
-                .unwrap();
+            actor.role = role;
         }
-        for actor in Self::get_actors() {
-            let mut output = format!(
-                "Init {} for {} (ID {})",
-                actor.role.name(),
-                actor.name,
-                actor.id
+        self.state.actors.sort_by_key(|actor| actor.id);
+        self.frontend.actors_initialized(&self.state);
+        for actor in &self.state.actors {
+            let model = match &actor.kind {
+                ActorKind::Llm(llm) => Some(llm.model_id.as_str()),
+                ActorKind::Real | ActorKind::Scripted => None,
+            };
+            tracing::info!(
+                actor_id = actor.id,
+                name = %actor.name,
+                role = %actor.role.name(),
+                model,
+                "Actor initialized"
             );
-            if let ActorKind::Llm(llm) = &actor.kind {
-                output.push_str(&format!(" (Model: {})", llm.model_id));
-            }
-            godot::global::godot_print!("{}", output);
         }
     }
 
     pub fn init_context(&mut self, start_at_night: bool) {
         self.add_to_context(ContextEntry {
-            content: utter_beginning(Self::get_actors().len() as u8, EXTRA_MESSAGES),
+            content: utter_beginning(
+                &self.prompts,
+                self.state.actors.len() as u8,
+                self.setup.extra_messages,
+            ),
             sayer_type: SayerType::System,
             extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
         });
-        let mut roles = Self::get_actors()
+        let mut roles = self
+            .state
+            .actors
             .iter()
-            .map(|actor| &actor.role)
+            .map(|actor| self.state.role_of(actor))
             .collect::<Vec<_>>();
-        roles.sort_by_key(|role| role.name());
-        roles.dedup_by(|role, other_role| role.name().eq(&other_role.name()));
+        roles.sort_by_key(|role| role.name.clone());
+        roles.dedup_by(|role, other_role| role.name == other_role.name);
         self.add_to_context(ContextEntry {
             content: build_role_list(&roles),
             sayer_type: SayerType::System,
             extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
         });
         self.add_to_context(ContextEntry {
-            content: build_actor_list(Self::get_actors()),
+            content: build_actor_list(&self.state.actors),
             sayer_type: SayerType::System,
             extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
         });
-        let mafias = Self::get_actors()
+        let mafias = self
+            .state
+            .actors
             .iter()
             .filter(|actor| {
-                matches!(
-                    actor.role.alignment(),
-                    crate::data::roles::RoleAlignment::Mafia
-                )
+                self.state.role_of(actor).alignment == crate::data::roles::RoleAlignment::Mafia
             })
             .collect::<Vec<_>>();
         self.add_to_context(ContextEntry {
@@ -96,9 +90,10 @@ impl Game {
             sayer_type: SayerType::System,
             extra_data: vec![ExtraData::SaidInChannel(Channel::Mafia)],
         });
-        for actor in Self::get_actors() {
+        for index in 0..self.state.actors.len() {
+            let actor = &self.state.actors[index];
             self.add_to_context(ContextEntry {
-                content: introduce_you(actor),
+                content: introduce_you(&self.prompts, actor, self.state.role_of(actor)),
                 sayer_type: SayerType::System,
                 extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(actor.id))],
             })
@@ -108,3 +103,14 @@ impl Game {
         }
     }
 }
+
+/// Dealt in order to the first actors after shuffling, everyone past the pool is a `Villager`
+pub fn default_role_pool() -> Vec<GameRole> {
+    vec![
+        GameRole::MAFIOSO,
+        GameRole::MAFIOSO,
+        GameRole::MAFIOSO,
+        GameRole::DOCTOR,
+        GameRole::SHERIFF,
+    ]
+}
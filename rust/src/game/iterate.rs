@@ -1,46 +1,84 @@
-use crate::actor::BaseActor;
 use crate::data::channel::Channel;
 use crate::data::context_entry::{ContextEntry, SayerType};
+use crate::data::event::{DeathCause, GameEvent};
 use crate::data::extra_data::ExtraData;
-use crate::game::{EXTRA_MESSAGES, Game};
-use crate::llm::tools::Tool;
-use crate::prompts::general::{actor_was_killed, day_time, night_time, voting_begins, voting_ends};
-use crate::prompts::specific::doctor::{target_protected, you_chose_to_protect};
+use crate::data::roles::{NightActionKind, WinCondition};
+use crate::game::Game;
+use crate::game::beliefs::ProbePoint;
+use crate::game::night::PendingAction;
+use crate::prompts::general::{
+    actor_was_killed, day_time, night_time, personal_win, voting_begins, voting_ends,
+};
 use crate::prompts::specific::mafia::mafia_discussion_begin;
 
 impl Game {
+    #[tracing::instrument(
+        name = "phase",
+        skip_all,
+        fields(
+            is_night = self.day_night_count.is_night,
+            number = if self.day_night_count.is_night {
+                self.day_night_count.night_count
+            } else {
+                self.day_night_count.day_count
+            },
+        )
+    )]
     pub async fn iterate(&mut self) {
+        self.checkpoint();
         if self.day_night_count.is_night {
             self.add_to_context(ContextEntry {
-                content: night_time(self.day_night_count.night_count),
+                content: night_time(&self.prompts, self.day_night_count.night_count),
                 sayer_type: SayerType::System,
                 extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
             });
+            self.emit(GameEvent::PhaseStarted {
+                is_night: true,
+                number: self.day_night_count.night_count,
+            });
         } else {
             if let Some(last_kill) = self.last_kill.take() {
                 for actor_id in last_kill {
-                    let actor = Self::get_actor_from_id(actor_id).unwrap();
+                    let actor = self.state.get_actor_from_id(actor_id).unwrap();
+                    // The night's kills can come from the Mafia and from several roles
+                    let killer = self
+                        .state
+                        .events
+                        .iter()
+                        .rev()
+                        .find_map(|event| match event {
+                            GameEvent::Killed {
+                                actor_id: killed,
+                                cause: DeathCause::NightAction { attacker },
+                            } if *killed == actor_id => self.state.get_actor_from_id(*attacker),
+                            _ => None,
+                        });
                     self.add_to_context(ContextEntry {
-                        content: actor_was_killed(actor),
+                        content: actor_was_killed(actor, killer.map(|killer| &killer.role)),
                         sayer_type: SayerType::System,
                         extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
                     });
                 }
             }
             self.add_to_context(ContextEntry {
-                content: day_time(self.day_night_count.day_count),
+                content: day_time(&self.prompts, self.day_night_count.day_count),
                 sayer_type: SayerType::System,
                 extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
             });
+            self.emit(GameEvent::PhaseStarted {
+                is_night: false,
+                number: self.day_night_count.day_count,
+            });
         }
-        self.command_sender
-            .send(crate::chat::ChatCommand::RefreshActorList)
-            .unwrap();
-        for actor in Self::get_actors_mut() {
-            actor.extra_data.clear();
+        self.frontend.actor_list_changed(&self.state);
+        for actor in &mut self.state.actors {
+            actor
+                .extra_data
+                .retain(|data| matches!(data, ExtraData::Dead | ExtraData::NightActionUsed));
         }
         if let Some(end) = self.check_end() {
-            self.end_result = Some(end);
+            self.end_result = Some(end.clone());
+            self.emit(GameEvent::GameEnded { result: end });
             return;
         }
         if self.day_night_count.is_night {
@@ -51,19 +89,19 @@ impl Game {
     }
 
     pub async fn iterate_night(&mut self) {
-        let actors = Self::get_nondead_actors();
-        self.process_sheriff_turn(&actors).await;
-        self.process_doctor_turn(&actors).await;
-        self.process_mafia_turn().await;
+        let mut pending = self.collect_night_actions().await;
+        pending.extend(self.process_mafia_turn().await);
+        self.resolve_night(pending);
+        self.probe_beliefs(ProbePoint::NightEnd).await;
         self.day_night_count.night_count += 1;
         self.day_night_count.is_night = false;
     }
 
     pub async fn iterate_day(&mut self) {
         self.run_discussion(
-            &Self::get_nondead_actors(),
-            Self::get_actors().len() as u8,
-            EXTRA_MESSAGES,
+            &self.state.get_nondead_ids(),
+            self.state.actors.len() as u8,
+            self.setup.extra_messages,
             vec![ExtraData::SaidInChannel(Channel::Global)],
         )
         .await;
@@ -74,132 +112,127 @@ impl Game {
         });
         if let Some(voted_out) = self
             .handle_voting(
-                &Self::get_nondead_actors(),
+                &self.state.get_nondead_ids(),
                 &[ExtraData::SaidInChannel(Channel::Global)],
             )
             .await
         {
-            Self::get_actors_mut()[voted_out as usize]
+            self.state
+                .get_actor_from_id_mut(voted_out)
+                .unwrap()
                 .extra_data
                 .push(ExtraData::Dead);
             self.add_to_context(ContextEntry {
-                content: voting_ends(Some(Self::get_actor_from_id(voted_out).unwrap()), true),
+                content: voting_ends(Some(self.state.get_actor_from_id(voted_out).unwrap()), true),
                 sayer_type: SayerType::System,
                 extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
-            })
+            });
+            self.emit(GameEvent::VoteEnded {
+                channel: Channel::Global,
+                target: Some(voted_out),
+            });
+            self.emit(GameEvent::Killed {
+                actor_id: voted_out,
+                cause: DeathCause::VotedOut,
+            });
+            let actor = self.state.get_actor_from_id(voted_out).unwrap();
+            if self.state.role_of(actor).win_condition == Some(WinCondition::VotedOut) {
+                self.add_to_context(ContextEntry {
+                    content: personal_win(actor, WinCondition::VotedOut),
+                    sayer_type: SayerType::System,
+                    extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
+                });
+                self.emit(GameEvent::WinConditionMet {
+                    actor_id: voted_out,
+                    condition: WinCondition::VotedOut,
+                });
+            }
         } else {
             self.add_to_context(ContextEntry {
                 content: voting_ends(None, false),
                 sayer_type: SayerType::System,
                 extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
-            })
+            });
+            self.emit(GameEvent::VoteEnded {
+                channel: Channel::Global,
+                target: None,
+            });
         }
+        self.probe_beliefs(ProbePoint::DayEnd).await;
         self.day_night_count.day_count += 1;
         self.day_night_count.is_night = true;
     }
 }
 
 impl Game {
-    async fn process_sheriff_turn(&mut self, actors: &Vec<&BaseActor>) {
-        let sheriffs = actors
-            .iter()
-            .filter(|a| matches!(a.role, crate::data::roles::GameRole::Sheriff))
-            .collect::<Vec<_>>();
-        for sheriff in sheriffs {
-            let action = sheriff
-                .prompt(
-                    crate::prompts::specific::sheriff::pick_to_investigate(),
-                    self,
-                    &[crate::llm::tools::ProvideID::make_tool()],
-                )
-                .await;
-            if let crate::data::action::Action::ProvideID(target_id) = action {
-                let target = Self::get_actor_from_id(target_id).unwrap();
-                self.add_to_context(ContextEntry {
-                    content: crate::prompts::specific::sheriff::investigate_result(target)
-                        .to_string(),
-                    sayer_type: SayerType::System,
-                    extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(sheriff.id))],
-                });
-            }
-        }
-    }
-
-    async fn process_doctor_turn(&mut self, actors: &Vec<&BaseActor>) {
-        let doctors = actors
-            .iter()
-            .filter(|a| matches!(a.role, crate::data::roles::GameRole::Doctor))
-            .collect::<Vec<_>>();
-        for doctor in doctors {
-            let action = doctor
-                .prompt(
-                    crate::prompts::specific::doctor::pick_to_protect(),
-                    self,
-                    &[crate::llm::tools::ProvideID::make_tool()],
-                )
-                .await;
-            if let crate::data::action::Action::ProvideID(target_id) = action {
-                let target = &mut Self::get_actors_mut()[target_id as usize];
-                self.add_to_context(ContextEntry {
-                    content: you_chose_to_protect(target),
-                    sayer_type: SayerType::System,
-                    extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(doctor.id))],
-                });
-                target.extra_data.push(ExtraData::ProtectedByDoctor);
-            }
-        }
-    }
-
-    async fn process_mafia_turn(&mut self) {
-        let actors = Self::get_nondead_actors();
-        let mafias = actors
+    /// The kill the Mafia voted for, carried out by everyone who voted for it
+    async fn process_mafia_turn(&mut self) -> Option<PendingAction> {
+        let mafias = self
+            .state
+            .get_nondead_actors()
             .into_iter()
-            .filter(|a| matches!(a.role.alignment(), crate::data::roles::RoleAlignment::Mafia))
+            .filter(|a| self.state.role_of(a).alignment == crate::data::roles::RoleAlignment::Mafia)
+            .map(|a| a.id)
             .collect::<Vec<_>>();
+        if mafias.is_empty() {
+            return None;
+        }
         self.add_to_context(ContextEntry {
-            content: mafia_discussion_begin().to_string(),
+            content: mafia_discussion_begin(&self.prompts),
             sayer_type: SayerType::System,
             extra_data: vec![ExtraData::SaidInChannel(Channel::Mafia)],
         });
         self.run_discussion(
             &mafias,
             mafias.len() as u8,
-            EXTRA_MESSAGES,
+            self.setup.extra_messages,
             vec![ExtraData::SaidInChannel(Channel::Mafia)],
         )
         .await;
+        let vote_start = self.state.events.len();
         if let Some(voted_out) = self
             .handle_voting(&mafias, &[ExtraData::SaidInChannel(Channel::Mafia)])
             .await
         {
-            let actor = Self::get_actor_from_id(voted_out).unwrap();
+            let actor = self.state.get_actor_from_id(voted_out).unwrap();
             self.add_to_context(ContextEntry {
                 content: voting_ends(Some(actor), false),
                 sayer_type: SayerType::System,
                 extra_data: vec![ExtraData::SaidInChannel(Channel::Mafia)],
             });
-            if actor
-                .extra_data
+            self.emit(GameEvent::VoteEnded {
+                channel: Channel::Mafia,
+                target: Some(voted_out),
+            });
+            let performers = self.state.events[vote_start..]
                 .iter()
-                .any(|data| matches!(data, ExtraData::ProtectedByDoctor))
-            {
-                self.add_to_context(ContextEntry {
-                    content: target_protected(actor),
-                    sayer_type: SayerType::System,
-                    extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
-                });
-            } else {
-                Self::get_actors_mut()[voted_out as usize]
-                    .extra_data
-                    .push(ExtraData::Dead);
-                self.last_kill = Some(vec![voted_out]);
-            }
+                .filter_map(|event| match event {
+                    GameEvent::Voted {
+                        voter,
+                        channel: Channel::Mafia,
+                        target: Some(target),
+                        ..
+                    } if *target == voted_out => Some(*voter),
+                    _ => None,
+                })
+                .collect();
+            Some(PendingAction {
+                actor_id: None,
+                performers,
+                kind: NightActionKind::Kill,
+                target: voted_out,
+            })
         } else {
             self.add_to_context(ContextEntry {
                 content: voting_ends(None, false),
                 sayer_type: SayerType::System,
                 extra_data: vec![ExtraData::SaidInChannel(Channel::Mafia)],
-            })
+            });
+            self.emit(GameEvent::VoteEnded {
+                channel: Channel::Mafia,
+                target: None,
+            });
+            None
         }
     }
 }
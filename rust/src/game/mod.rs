@@ -1,33 +1,97 @@
 mod access;
+pub mod autosave;
+pub mod beliefs;
 mod discussion;
-mod init;
+pub mod events;
+pub mod fork;
+pub mod init;
 mod iterate;
+mod night;
+pub mod save;
+pub mod setup;
 mod voting;
 
-use crate::actor::BaseActor;
-use crate::chat::ChatCommand;
-use crate::data::context_entry::ContextEntry;
-use crate::data::roles::RoleAlignment;
-use std::sync::mpsc::Sender;
+use crate::actor::{ActorKind, BaseActor};
+use crate::data::action::Action;
+use crate::data::channel::Channel;
+use crate::data::context_entry::{ContextEntry, SayerType};
+use crate::data::event::GameEvent;
+use crate::data::extra_data::ExtraData;
+use crate::data::roles::{RoleAlignment, RoleBook, WinCondition};
+use crate::frontend::{GameFrontend, Recovery, TurnFailure};
+use crate::game::autosave::Autosave;
+use crate::game::beliefs::ProbePoint;
+use crate::game::events::EventSubscriber;
+use crate::game::save::{ParentLink, PhaseCheckpoint, new_game_id};
+use crate::game::setup::GameSetup;
+use crate::llm::cassette::Cassette;
+use crate::llm::tools::tool_name;
+use crate::prompts::PromptOverrides;
+use async_openai::types::chat::ChatCompletionTools;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
 
 const EXTRA_MESSAGES: u8 = 7;
+/// Days and nights in a row without a death after which a game with a lone killer alive is a draw
+const STALEMATE_PHASES: usize = 6;
 
-static mut ACTORS: Vec<BaseActor> = Vec::new();
-static mut CONTEXT: Vec<ContextEntry> = Vec::new();
+/// Every random choice in a game is drawn from this, seeded from [`Game::seed`]
+pub type GameRng = rand::rngs::Xoshiro256PlusPlus;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GameState {
+    pub actors: Vec<BaseActor>,
+    pub context: Vec<ContextEntry>,
+    pub events: Vec<GameEvent>,
+    /// The definitions of the actors' roles
+    pub roles: RoleBook,
+}
 
 pub struct Game {
-    pub command_sender: Sender<ChatCommand>,
+    pub id: String,
+    pub parent: Option<ParentLink>,
+    pub state: GameState,
+    frontend: Box<dyn GameFrontend>,
+    subscribers: Vec<Box<dyn EventSubscriber>>,
     pub end_result: Option<EndResult>,
+    pub seed: u64,
+    /// Records or replays every LLM response of this game
+    pub cassette: Option<Cassette>,
+    pub prompts: PromptOverrides,
+    pub setup: GameSetup,
+    /// Where every living LLM actor is privately asked who they suspect
+    pub belief_probes: Vec<ProbePoint>,
+    /// Written at every phase boundary when set
+    pub autosave: Option<Autosave>,
+    /// The failed turn the game was given up on. Every later prompt is answered with an abstain
+    /// so the phase winds down, and the game can't go on after it.
+    pub aborted: Option<TurnFailure>,
+    rng: GameRng,
+    checkpoints: Vec<PhaseCheckpoint>,
     playable_actor: Option<u8>,
     last_kill: Option<Vec<u8>>,
     day_night_count: DayNightCount,
 }
 
 impl Game {
-    pub fn new(command_sender: Sender<ChatCommand>, playable_actor: Option<u8>) -> Self {
+    pub fn new(frontend: Box<dyn GameFrontend>, playable_actor: Option<u8>, seed: u64) -> Self {
         Self {
-            command_sender,
+            id: new_game_id(),
+            parent: None,
+            state: GameState::default(),
+            frontend,
+            subscribers: Vec::new(),
             end_result: None,
+            seed,
+            cassette: None,
+            prompts: PromptOverrides::default(),
+            setup: GameSetup::default(),
+            belief_probes: Vec::new(),
+            autosave: None,
+            aborted: None,
+            rng: GameRng::seed_from_u64(seed),
+            checkpoints: Vec::new(),
             playable_actor,
             last_kill: None,
             day_night_count: DayNightCount {
@@ -38,37 +102,384 @@ impl Game {
         }
     }
 
+    /// Ends the game once a faction has won, a lone killer can no longer be beaten or nobody is left.
+    /// A game with a lone killer alive also ends in a draw after [`STALEMATE_PHASES`] without a death,
+    /// such as a killer facing a Doctor who keeps protecting the one it attacks.
+    /// The players who met their own win condition so far win too.
     pub fn check_end(&mut self) -> Option<EndResult> {
-        let actors = Self::get_nondead_actors();
-        let townies = actors
+        let actors = self.state.get_nondead_actors();
+        let loners = actors
             .iter()
-            .filter(|actor| !matches!(actor.role.alignment(), RoleAlignment::Mafia))
+            .filter(|actor| {
+                self.state.role_of(actor).win_condition == Some(WinCondition::LastStanding)
+            })
+            .map(|actor| actor.id)
             .collect::<Vec<_>>();
         let mafias = actors
             .iter()
-            .filter(|actor| matches!(actor.role.alignment(), RoleAlignment::Mafia))
-            .collect::<Vec<_>>();
-        if townies.len() == mafias.len() {
-            Some(EndResult::Mafia)
-        } else if mafias.is_empty() {
-            Some(EndResult::Town)
-        } else {
+            .filter(|actor| self.state.role_of(actor).alignment == RoleAlignment::Mafia)
+            .count();
+        let others = actors.len() - mafias;
+        let mut last_standing = None;
+        // Several kills a night can take the Mafia past parity without ever reaching it
+        let faction = if actors.is_empty() {
             None
+        } else if !loners.is_empty() {
+            // Neither faction can win while a lone killer lives. Once one other player is left,
+            // they can't outvote the killer and the killer wins the night.
+            if loners.len() == 1 && actors.len() <= 2 {
+                last_standing = Some(loners[0]);
+            } else if self.phases_without_death() < STALEMATE_PHASES {
+                return None;
+            }
+            None
+        } else if mafias == 0 {
+            Some(RoleAlignment::Town)
+        } else if others <= mafias {
+            Some(RoleAlignment::Mafia)
+        } else {
+            return None;
+        };
+        let mut personal_wins = self
+            .state
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::WinConditionMet {
+                    actor_id,
+                    condition,
+                } => Some(PersonalWin {
+                    actor_id: *actor_id,
+                    condition: *condition,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if let Some(actor_id) = last_standing {
+            personal_wins.push(PersonalWin {
+                actor_id,
+                condition: WinCondition::LastStanding,
+            });
+        }
+        Some(EndResult {
+            faction,
+            personal_wins,
+        })
+    }
+
+    /// Counts the phases started since the last death, the current one included
+    fn phases_without_death(&self) -> usize {
+        self.state
+            .events
+            .iter()
+            .rev()
+            .take_while(|event| !matches!(event, GameEvent::Killed { .. }))
+            .filter(|event| matches!(event, GameEvent::PhaseStarted { .. }))
+            .count()
+    }
+
+    pub fn frontend(&self) -> &dyn GameFrontend {
+        self.frontend.as_ref()
+    }
+
+    pub fn rng(&mut self) -> &mut GameRng {
+        &mut self.rng
+    }
+
+    pub async fn prompt_actor(
+        &mut self,
+        actor_id: u8,
+        prompt: &str,
+        tools: &[ChatCompletionTools],
+    ) -> Action {
+        if self.aborted.is_some() {
+            return Action::Abstain;
+        }
+        loop {
+            let actor = self.state.get_actor_from_id(actor_id).unwrap();
+            let context_len = self.state.context.len();
+            let model_id = match &actor.kind {
+                ActorKind::Llm(llm) => Some(llm.model_id.clone()),
+                ActorKind::Real | ActorKind::Scripted => None,
+            };
+            let span = tracing::info_span!(
+                "turn",
+                actor_id,
+                name = %actor.name,
+                role = %actor.role.name(),
+            );
+            let cause = match actor.prompt(prompt, self, tools).instrument(span).await {
+                Ok(reply) => match self.state.unknown_id(&reply.action) {
+                    None => {
+                        if let Some(raw) = reply.raw {
+                            self.add_to_context(raw);
+                        }
+                        if let Some(model_id) = model_id {
+                            self.emit(GameEvent::Decided {
+                                actor_id,
+                                model_id,
+                                context_len,
+                                prompt: prompt.to_string(),
+                                tools: tools.iter().filter_map(tool_name).collect(),
+                                tool_calls: reply.tool_calls,
+                            });
+                        }
+                        return reply.action;
+                    }
+                    Some(id) => format!("ID {} does not belong to any player", id),
+                },
+                Err(err) => err.to_string(),
+            };
+            let failure = TurnFailure {
+                actor_id,
+                name: actor.name.clone(),
+                model_id,
+                cause,
+            };
+            tracing::error!("{}", failure);
+            match self.frontend.turn_failed(&failure).await {
+                Recovery::Retry => {}
+                Recovery::Skip => return Action::Abstain,
+                Recovery::ReplaceModel(model_id) => {
+                    if let ActorKind::Llm(llm) =
+                        &mut self.state.get_actor_from_id_mut(actor_id).unwrap().kind
+                    {
+                        tracing::info!(actor_id, model = %model_id, "Replacing the model");
+                        llm.model_id = model_id;
+                    }
+                }
+                Recovery::Abort => {
+                    self.aborted = Some(failure);
+                    return Action::Abstain;
+                }
+            }
+        }
+    }
+
+    /// Everything logged while this game runs belongs in this span
+    pub fn span(&self) -> tracing::Span {
+        tracing::info_span!("game", id = %self.id, seed = self.seed)
+    }
+
+    pub async fn run_to_end(&mut self) {
+        let span = self.span();
+        async {
+            while self.end_result.is_none() && self.aborted.is_none() {
+                self.iterate().await;
+            }
+            self.announce_end();
         }
+        .instrument(span)
+        .await
     }
 
-    pub fn send_on_behalf_of_chat(&self, command: ChatCommand) {
-        self.command_sender.send(command).unwrap();
+    pub fn announce_end(&mut self) {
+        if let Some(end_result) = &self.end_result {
+            self.add_to_context(ContextEntry {
+                content: crate::prompts::general::game_end(end_result, &self.state.actors),
+                sayer_type: SayerType::System,
+                extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
+            });
+            let save = self.to_save();
+            // So the finished game is no longer offered to be continued
+            self.write_autosave(&save);
+            self.frontend.game_ended(&save);
+        }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DayNightCount {
     pub day_count: u8,
     pub night_count: u8,
     pub is_night: bool,
 }
 
-pub enum EndResult {
-    Mafia,
-    Town,
+/// Who won a finished game and why
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EndResult {
+    /// `None` when no faction won
+    pub faction: Option<RoleAlignment>,
+    /// Players who won on their own, whether or not their faction did
+    pub personal_wins: Vec<PersonalWin>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PersonalWin {
+    pub actor_id: u8,
+    pub condition: WinCondition,
+}
+
+impl EndResult {
+    /// `roles` are those of the game, which define the actor's
+    pub fn is_win_for(&self, actor: &BaseActor, roles: &RoleBook) -> bool {
+        let alignment = roles[&actor.role].alignment.clone();
+        (alignment != RoleAlignment::Neutral && self.faction.as_ref() == Some(&alignment))
+            || self
+                .personal_wins
+                .iter()
+                .any(|win| win.actor_id == actor.id)
+    }
+
+    /// Such as "Town won" or "Mafia won, plus 1 personal win"
+    pub fn label(&self) -> String {
+        let mut label = match &self.faction {
+            Some(RoleAlignment::Town) => String::from("Town won"),
+            Some(RoleAlignment::Mafia) => String::from("Mafia won"),
+            Some(RoleAlignment::Neutral) | None => String::from("No faction won"),
+        };
+        match self.personal_wins.len() {
+            0 => {}
+            1 => label.push_str(", plus 1 personal win"),
+            count => label.push_str(&format!(", plus {} personal wins", count)),
+        }
+        label
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::night::tests::{SERIAL_KILLER, game_with};
+    use super::{Game, STALEMATE_PHASES};
+    use crate::data::event::{DeathCause, GameEvent};
+    use crate::data::extra_data::ExtraData;
+    use crate::data::roles::{GameRole, RoleAlignment, WinCondition};
+    use std::borrow::Cow;
+
+    const JESTER: GameRole = GameRole(Cow::Borrowed("Jester"));
+
+    fn kill(game: &mut Game, id: u8) {
+        game.state
+            .get_actor_from_id_mut(id)
+            .unwrap()
+            .extra_data
+            .push(ExtraData::Dead);
+        game.state.events.push(GameEvent::Killed {
+            actor_id: id,
+            cause: DeathCause::VotedOut,
+        });
+    }
+
+    fn start_phases(game: &mut Game, count: usize) {
+        for number in 0..count {
+            game.state.events.push(GameEvent::PhaseStarted {
+                is_night: number % 2 == 1,
+                number: (number / 2) as u8,
+            });
+        }
+    }
+
+    fn personal_wins(game: &mut Game) -> Vec<(u8, WinCondition)> {
+        game.check_end()
+            .unwrap()
+            .personal_wins
+            .iter()
+            .map(|win| (win.actor_id, win.condition))
+            .collect()
+    }
+
+    #[test]
+    fn goes_on_while_town_outnumbers_mafia() {
+        let mut game = game_with(&[GameRole::MAFIOSO, GameRole::DOCTOR, GameRole::VILLAGER]);
+        assert!(game.check_end().is_none());
+    }
+
+    #[test]
+    fn town_wins_once_the_mafia_is_gone() {
+        let mut game = game_with(&[GameRole::MAFIOSO, GameRole::DOCTOR, GameRole::VILLAGER]);
+        kill(&mut game, 0);
+        assert_eq!(game.check_end().unwrap().faction, Some(RoleAlignment::Town));
+    }
+
+    #[test]
+    fn mafia_wins_at_parity() {
+        let mut game = game_with(&[GameRole::MAFIOSO, GameRole::DOCTOR, GameRole::VILLAGER]);
+        kill(&mut game, 1);
+        assert_eq!(
+            game.check_end().unwrap().faction,
+            Some(RoleAlignment::Mafia)
+        );
+    }
+
+    #[test]
+    fn neutral_players_count_against_the_mafia() {
+        let mut game = game_with(&[GameRole::MAFIOSO, JESTER, GameRole::VILLAGER]);
+        assert!(game.check_end().is_none());
+        kill(&mut game, 2);
+        assert_eq!(
+            game.check_end().unwrap().faction,
+            Some(RoleAlignment::Mafia)
+        );
+    }
+
+    #[test]
+    fn nobody_left_is_a_draw() {
+        let mut game = game_with(&[GameRole::MAFIOSO, GameRole::VILLAGER]);
+        kill(&mut game, 0);
+        kill(&mut game, 1);
+        let result = game.check_end().unwrap();
+        assert_eq!(result.faction, None);
+        assert!(result.personal_wins.is_empty());
+    }
+
+    #[test]
+    fn personal_wins_carry_over_to_the_faction_win() {
+        let mut game = game_with(&[GameRole::MAFIOSO, JESTER, GameRole::VILLAGER]);
+        kill(&mut game, 1);
+        game.state.events.push(GameEvent::WinConditionMet {
+            actor_id: 1,
+            condition: WinCondition::VotedOut,
+        });
+        kill(&mut game, 0);
+        assert_eq!(personal_wins(&mut game), vec![(1, WinCondition::VotedOut)]);
+    }
+
+    #[test]
+    fn no_faction_wins_while_a_lone_killer_can_be_outvoted() {
+        let mut game = game_with(&[SERIAL_KILLER, GameRole::MAFIOSO, GameRole::VILLAGER]);
+        assert!(game.check_end().is_none());
+        let mut game = game_with(&[SERIAL_KILLER, GameRole::DOCTOR, GameRole::VILLAGER]);
+        assert!(game.check_end().is_none());
+    }
+
+    #[test]
+    fn lone_killer_wins_as_the_last_one_standing() {
+        let mut game = game_with(&[SERIAL_KILLER, GameRole::MAFIOSO]);
+        kill(&mut game, 1);
+        let result = game.check_end().unwrap();
+        assert_eq!(result.faction, None);
+        assert_eq!(
+            personal_wins(&mut game),
+            vec![(0, WinCondition::LastStanding)]
+        );
+    }
+
+    #[test]
+    fn lone_killer_wins_one_on_one() {
+        let mut game = game_with(&[SERIAL_KILLER, GameRole::DOCTOR, GameRole::VILLAGER]);
+        kill(&mut game, 2);
+        let result = game.check_end().unwrap();
+        assert_eq!(result.faction, None);
+        assert_eq!(
+            personal_wins(&mut game),
+            vec![(0, WinCondition::LastStanding)]
+        );
+    }
+
+    #[test]
+    fn lone_killer_game_without_deaths_is_a_stalemate() {
+        let mut game = game_with(&[
+            SERIAL_KILLER,
+            GameRole::DOCTOR,
+            GameRole::DOCTOR,
+            GameRole::VILLAGER,
+        ]);
+        kill(&mut game, 3);
+        start_phases(&mut game, STALEMATE_PHASES - 1);
+        assert!(game.check_end().is_none());
+        start_phases(&mut game, 1);
+        let result = game.check_end().unwrap();
+        assert_eq!(result.faction, None);
+        assert!(result.personal_wins.is_empty());
+    }
 }
@@ -0,0 +1,554 @@
+use crate::data::action::Action;
+use crate::data::channel::Channel;
+use crate::data::context_entry::{ContextEntry, SayerType};
+use crate::data::event::{ActionOutcome, DeathCause, GameEvent, ResolvedAction};
+use crate::data::extra_data::ExtraData;
+use crate::data::roles::{NightActionKind, TargetRule};
+use crate::game::Game;
+use crate::llm::tools::pick_tools;
+use crate::prompts::specific::doctor::{pick_to_protect, target_protected, you_chose_to_protect};
+use crate::prompts::specific::night::{
+    invalid_target, pick_to_block, pick_to_kill, uses_left, you_chose_to_block, you_chose_to_kill,
+    you_were_blocked,
+};
+use crate::prompts::specific::sheriff::{investigate_result, pick_to_investigate};
+use std::collections::BTreeSet;
+
+/// A night action that was picked but not resolved yet
+pub(crate) struct PendingAction {
+    /// `None` for the Mafia's shared kill
+    pub actor_id: Option<u8>,
+    /// The action fails if every one of them is blocked.
+    /// The Mafia's kill is carried out by everyone who voted for the target.
+    pub performers: Vec<u8>,
+    pub kind: NightActionKind,
+    pub target: u8,
+}
+
+impl Game {
+    /// Asks every living actor whose role has a night action for their target, by ID.
+    /// Nothing takes effect until [`Game::resolve_night`].
+    pub(crate) async fn collect_night_actions(&mut self) -> Vec<PendingAction> {
+        let actors = self
+            .state
+            .get_nondead_actors()
+            .into_iter()
+            .filter(|actor| self.state.role_of(actor).night_action.is_some())
+            .map(|actor| actor.id)
+            .collect::<Vec<_>>();
+        let mut pending = Vec::new();
+        for actor_id in actors {
+            pending.extend(self.pick_night_action(actor_id).await);
+        }
+        pending
+    }
+
+    async fn pick_night_action(&mut self, actor_id: u8) -> Option<PendingAction> {
+        let actor = self.state.get_actor_from_id(actor_id).unwrap();
+        let action = self.state.role_of(actor).night_action.clone()?;
+        let used = actor
+            .extra_data
+            .iter()
+            .filter(|data| matches!(data, ExtraData::NightActionUsed))
+            .count();
+        let remaining = action.uses.map(|uses| (uses as usize).saturating_sub(used));
+        if remaining == Some(0) {
+            return None;
+        }
+        let role = actor.role.name();
+        let mut prompt = action.prompt.clone().unwrap_or_else(|| match action.kind {
+            NightActionKind::Block => pick_to_block(&self.prompts, &role),
+            NightActionKind::Investigate => pick_to_investigate(&self.prompts, &role),
+            NightActionKind::Protect => pick_to_protect(&self.prompts, &role),
+            NightActionKind::Kill => pick_to_kill(&self.prompts, &role),
+        });
+        if let Some(remaining) = remaining {
+            prompt = format!("{} {}", prompt, uses_left(remaining));
+        }
+        let Action::ProvideID(target_id) =
+            self.prompt_actor(actor_id, &prompt, &pick_tools()).await
+        else {
+            return None;
+        };
+        let target = self.state.get_actor_from_id(target_id).unwrap();
+        if !self.can_target(actor_id, target_id, action.targets) {
+            self.add_to_context(ContextEntry {
+                content: invalid_target(target),
+                sayer_type: SayerType::System,
+                extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(actor_id))],
+            });
+            return None;
+        }
+        let acknowledgement = match action.kind {
+            NightActionKind::Block => Some(you_chose_to_block(target)),
+            NightActionKind::Protect => Some(you_chose_to_protect(target)),
+            NightActionKind::Kill => Some(you_chose_to_kill(target)),
+            // The result is the acknowledgement
+            NightActionKind::Investigate => None,
+        };
+        if let Some(content) = acknowledgement {
+            self.add_to_context(ContextEntry {
+                content,
+                sayer_type: SayerType::System,
+                extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(actor_id))],
+            });
+        }
+        // Spent even if the action is blocked
+        self.state
+            .get_actor_from_id_mut(actor_id)
+            .unwrap()
+            .extra_data
+            .push(ExtraData::NightActionUsed);
+        Some(PendingAction {
+            actor_id: Some(actor_id),
+            performers: vec![actor_id],
+            kind: action.kind,
+            target: target_id,
+        })
+    }
+
+    fn can_target(&self, actor_id: u8, target_id: u8, rule: TargetRule) -> bool {
+        if !self.state.get_nondead_ids().contains(&target_id) {
+            return false;
+        }
+        let actor = self.state.get_actor_from_id(actor_id).unwrap();
+        let target = self.state.get_actor_from_id(target_id).unwrap();
+        match rule {
+            TargetRule::Others => actor_id != target_id,
+            TargetRule::Anyone => true,
+            TargetRule::NotAllies => {
+                self.state.role_of(actor).alignment != self.state.role_of(target).alignment
+            }
+        }
+    }
+
+    /// Applies the night's actions together, kind by kind in the order of [`NightActionKind`]
+    /// and then by actor, with the Mafia's kill first among the kills.
+    /// Blocks always land, so no block depends on another. What happened is emitted as [`GameEvent::NightResolved`].
+    pub(crate) fn resolve_night(&mut self, mut pending: Vec<PendingAction>) {
+        pending.sort_by_key(|action| (action.kind, action.actor_id));
+        let mut blocked = BTreeSet::new();
+        let mut protected = BTreeSet::new();
+        let mut resolved = Vec::new();
+        for action in pending {
+            let is_blocked = action.kind != NightActionKind::Block
+                && !action.performers.is_empty()
+                && action.performers.iter().all(|id| blocked.contains(id));
+            let outcome = if is_blocked {
+                ActionOutcome::Blocked
+            } else {
+                self.apply_night_action(&action, &mut blocked, &mut protected)
+            };
+            tracing::debug!(
+                actor_id = ?action.actor_id,
+                kind = ?action.kind,
+                target = action.target,
+                ?outcome,
+                "Night action resolved"
+            );
+            resolved.push(ResolvedAction {
+                actor_id: action.actor_id,
+                kind: action.kind,
+                target: action.target,
+                outcome,
+            });
+        }
+        // Announced once per target, however many kills it stopped
+        let saved = resolved
+            .iter()
+            .filter(|action| action.outcome == ActionOutcome::Prevented)
+            .map(|action| action.target)
+            .collect::<BTreeSet<_>>();
+        for target in saved {
+            self.add_to_context(ContextEntry {
+                content: target_protected(self.state.get_actor_from_id(target).unwrap()),
+                sayer_type: SayerType::System,
+                extra_data: vec![ExtraData::SaidInChannel(Channel::Global)],
+            });
+        }
+        for target in blocked {
+            self.add_to_context(ContextEntry {
+                content: you_were_blocked().to_string(),
+                sayer_type: SayerType::System,
+                extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(target))],
+            });
+        }
+        self.emit(GameEvent::NightResolved { actions: resolved });
+    }
+
+    fn apply_night_action(
+        &mut self,
+        action: &PendingAction,
+        blocked: &mut BTreeSet<u8>,
+        protected: &mut BTreeSet<u8>,
+    ) -> ActionOutcome {
+        let target = self.state.get_actor_from_id(action.target).unwrap();
+        match action.kind {
+            NightActionKind::Block => {
+                blocked.insert(action.target);
+                if let Some(actor_id) = action.actor_id {
+                    self.emit(GameEvent::Blocked {
+                        blocker: actor_id,
+                        target: action.target,
+                    });
+                }
+            }
+            NightActionKind::Protect => {
+                protected.insert(action.target);
+                if let Some(actor_id) = action.actor_id {
+                    self.emit(GameEvent::Protected {
+                        doctor: actor_id,
+                        target: action.target,
+                    });
+                }
+            }
+            NightActionKind::Kill => {
+                if protected.contains(&action.target) {
+                    self.emit(GameEvent::KillPrevented {
+                        target: action.target,
+                    });
+                    return ActionOutcome::Prevented;
+                }
+                // Someone else already got to them tonight
+                if target
+                    .extra_data
+                    .iter()
+                    .any(|data| matches!(data, ExtraData::Dead))
+                {
+                    return ActionOutcome::Succeeded;
+                }
+                self.state
+                    .get_actor_from_id_mut(action.target)
+                    .unwrap()
+                    .extra_data
+                    .push(ExtraData::Dead);
+                self.last_kill
+                    .get_or_insert_with(Vec::new)
+                    .push(action.target);
+                self.emit(GameEvent::Killed {
+                    actor_id: action.target,
+                    cause: match action.actor_id {
+                        Some(attacker) => DeathCause::NightAction { attacker },
+                        None => DeathCause::Mafia,
+                    },
+                });
+            }
+            NightActionKind::Investigate => {
+                let alignment = self
+                    .setup
+                    .investigated_alignment(self.state.role_of(target));
+                // Only the Mafia's shared kill is pending without an actor
+                let actor_id = action
+                    .actor_id
+                    .expect("Investigations are picked by an actor");
+                self.add_to_context(ContextEntry {
+                    content: investigate_result(target, &alignment),
+                    sayer_type: SayerType::System,
+                    extra_data: vec![ExtraData::SaidInChannel(Channel::ToSelf(actor_id))],
+                });
+                self.emit(GameEvent::Investigated {
+                    sheriff: actor_id,
+                    target: action.target,
+                    alignment,
+                });
+            }
+        }
+        ActionOutcome::Succeeded
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::PendingAction;
+    use crate::actor::{ActorKind, BaseActor, ModelCustomization};
+    use crate::data::event::{ActionOutcome, GameEvent, ResolvedAction};
+    use crate::data::extra_data::ExtraData;
+    use crate::data::roles::{GameRole, NightActionKind, RoleAlignment};
+    use crate::frontend::headless::NullFrontend;
+    use crate::game::Game;
+    use crate::prompts::specific::doctor::target_protected;
+    use std::borrow::Cow;
+
+    pub(crate) const SERIAL_KILLER: GameRole = GameRole(Cow::Borrowed("Serial Killer"));
+
+    /// A game whose actors have `roles`, with IDs in the same order
+    pub(crate) fn game_with(roles: &[GameRole]) -> Game {
+        let mut game = Game::new(Box::new(NullFrontend), None, 0);
+        game.state.actors = roles
+            .iter()
+            .enumerate()
+            .map(|(id, role)| BaseActor {
+                name: format!("Player {}", id),
+                id: id as u8,
+                role: role.clone(),
+                extra_data: Vec::new(),
+                kind: ActorKind::Scripted,
+                model_customization: ModelCustomization {
+                    sprite_path: String::new(),
+                    color: [1.0, 1.0, 1.0, 1.0],
+                },
+            })
+            .collect();
+        game
+    }
+
+    fn action(actor_id: Option<u8>, kind: NightActionKind, target: u8) -> PendingAction {
+        PendingAction {
+            actor_id,
+            performers: actor_id.into_iter().collect(),
+            kind,
+            target,
+        }
+    }
+
+    fn mafia_kill(performers: &[u8], target: u8) -> PendingAction {
+        PendingAction {
+            actor_id: None,
+            performers: performers.to_vec(),
+            kind: NightActionKind::Kill,
+            target,
+        }
+    }
+
+    fn resolved(game: &Game) -> Vec<ResolvedAction> {
+        game.state
+            .events
+            .iter()
+            .rev()
+            .find_map(|event| match event {
+                GameEvent::NightResolved { actions } => Some(actions.clone()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn outcome(game: &Game, actor_id: Option<u8>, kind: NightActionKind) -> ActionOutcome {
+        resolved(game)
+            .into_iter()
+            .find(|action| action.actor_id == actor_id && action.kind == kind)
+            .unwrap()
+            .outcome
+    }
+
+    fn is_dead(game: &Game, id: u8) -> bool {
+        game.state
+            .get_actor_from_id(id)
+            .unwrap()
+            .extra_data
+            .iter()
+            .any(|data| matches!(data, ExtraData::Dead))
+    }
+
+    fn count_events(game: &Game, matches: impl Fn(&GameEvent) -> bool) -> usize {
+        game.state
+            .events
+            .iter()
+            .filter(|event| matches(event))
+            .count()
+    }
+
+    /// 0 Mafioso, 1 Doctor, 2 Sheriff, 3 and 4 Villagers, 5 Serial Killer, 6 Mafioso
+    fn lobby() -> Game {
+        game_with(&[
+            GameRole::MAFIOSO,
+            GameRole::DOCTOR,
+            GameRole::SHERIFF,
+            GameRole::VILLAGER,
+            GameRole::VILLAGER,
+            SERIAL_KILLER,
+            GameRole::MAFIOSO,
+        ])
+    }
+
+    #[test]
+    fn resolves_blocks_then_protections_then_kills_then_investigations() {
+        let mut game = lobby();
+        game.resolve_night(vec![
+            action(Some(2), NightActionKind::Investigate, 0),
+            action(Some(5), NightActionKind::Kill, 3),
+            mafia_kill(&[0], 4),
+            action(Some(1), NightActionKind::Protect, 4),
+            action(Some(3), NightActionKind::Block, 6),
+        ]);
+        let order = resolved(&game)
+            .iter()
+            .map(|action| (action.actor_id, action.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (Some(3), NightActionKind::Block),
+                (Some(1), NightActionKind::Protect),
+                (None, NightActionKind::Kill),
+                (Some(5), NightActionKind::Kill),
+                (Some(2), NightActionKind::Investigate),
+            ]
+        );
+        assert_eq!(
+            outcome(&game, None, NightActionKind::Kill),
+            ActionOutcome::Prevented
+        );
+        assert!(!is_dead(&game, 4));
+        assert!(is_dead(&game, 3));
+    }
+
+    #[test]
+    fn blocked_actors_do_nothing() {
+        let mut game = lobby();
+        game.resolve_night(vec![
+            action(Some(3), NightActionKind::Block, 2),
+            action(Some(4), NightActionKind::Block, 1),
+            action(Some(2), NightActionKind::Investigate, 0),
+            action(Some(1), NightActionKind::Protect, 3),
+            mafia_kill(&[0], 3),
+        ]);
+        assert_eq!(
+            outcome(&game, Some(2), NightActionKind::Investigate),
+            ActionOutcome::Blocked
+        );
+        assert_eq!(
+            outcome(&game, Some(1), NightActionKind::Protect),
+            ActionOutcome::Blocked
+        );
+        assert_eq!(
+            count_events(&game, |event| matches!(
+                event,
+                GameEvent::Investigated { .. }
+            )),
+            0
+        );
+        // The Doctor was blocked, so the protection didn't stop the kill
+        assert!(is_dead(&game, 3));
+    }
+
+    #[test]
+    fn mafia_kill_is_blocked_only_when_every_performer_is() {
+        let mut game = lobby();
+        game.resolve_night(vec![
+            action(Some(3), NightActionKind::Block, 0),
+            mafia_kill(&[0, 6], 4),
+        ]);
+        assert_eq!(
+            outcome(&game, None, NightActionKind::Kill),
+            ActionOutcome::Succeeded
+        );
+        assert!(is_dead(&game, 4));
+
+        let mut game = lobby();
+        game.resolve_night(vec![
+            action(Some(3), NightActionKind::Block, 0),
+            action(Some(4), NightActionKind::Block, 6),
+            mafia_kill(&[0, 6], 2),
+        ]);
+        assert_eq!(
+            outcome(&game, None, NightActionKind::Kill),
+            ActionOutcome::Blocked
+        );
+        assert!(!is_dead(&game, 2));
+    }
+
+    #[test]
+    fn double_kill_on_one_target_kills_once() {
+        let mut game = lobby();
+        game.resolve_night(vec![
+            action(Some(5), NightActionKind::Kill, 3),
+            mafia_kill(&[0], 3),
+        ]);
+        assert_eq!(
+            outcome(&game, None, NightActionKind::Kill),
+            ActionOutcome::Succeeded
+        );
+        assert_eq!(
+            outcome(&game, Some(5), NightActionKind::Kill),
+            ActionOutcome::Succeeded
+        );
+        assert!(is_dead(&game, 3));
+        assert_eq!(
+            count_events(&game, |event| matches!(event, GameEvent::Killed { .. })),
+            1
+        );
+    }
+
+    #[test]
+    fn kills_on_different_targets_both_land() {
+        let mut game = lobby();
+        game.resolve_night(vec![
+            action(Some(5), NightActionKind::Kill, 3),
+            mafia_kill(&[0], 4),
+        ]);
+        assert!(is_dead(&game, 3));
+        assert!(is_dead(&game, 4));
+        assert_eq!(
+            count_events(&game, |event| matches!(event, GameEvent::Killed { .. })),
+            2
+        );
+    }
+
+    #[test]
+    fn protection_stops_every_kill_and_is_announced_once() {
+        let mut game = lobby();
+        game.resolve_night(vec![
+            action(Some(1), NightActionKind::Protect, 3),
+            action(Some(5), NightActionKind::Kill, 3),
+            mafia_kill(&[0], 3),
+        ]);
+        assert_eq!(
+            outcome(&game, None, NightActionKind::Kill),
+            ActionOutcome::Prevented
+        );
+        assert_eq!(
+            outcome(&game, Some(5), NightActionKind::Kill),
+            ActionOutcome::Prevented
+        );
+        assert!(!is_dead(&game, 3));
+        assert_eq!(
+            count_events(&game, |event| matches!(
+                event,
+                GameEvent::KillPrevented { .. }
+            )),
+            2
+        );
+        let notice = target_protected(game.state.get_actor_from_id(3).unwrap());
+        let notices = game
+            .state
+            .context
+            .iter()
+            .filter(|entry| entry.content == notice)
+            .count();
+        assert_eq!(notices, 1);
+    }
+
+    fn investigations(game: &Game) -> Vec<(u8, RoleAlignment)> {
+        game.state
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Investigated {
+                    target, alignment, ..
+                } => Some((*target, alignment.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn serial_killer_looks_innocent_by_default() {
+        let mut game = lobby();
+        game.resolve_night(vec![action(Some(2), NightActionKind::Investigate, 5)]);
+        assert_eq!(investigations(&game), vec![(5, RoleAlignment::Neutral)]);
+    }
+
+    #[test]
+    fn serial_killer_looks_suspicious_when_the_setup_says_so() {
+        let mut game = lobby();
+        game.setup.neutral_killers_suspicious = true;
+        game.resolve_night(vec![
+            action(Some(2), NightActionKind::Investigate, 5),
+            action(Some(2), NightActionKind::Investigate, 3),
+        ]);
+        assert_eq!(
+            investigations(&game),
+            vec![(5, RoleAlignment::Mafia), (3, RoleAlignment::Town)]
+        );
+    }
+}
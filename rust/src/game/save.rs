@@ -0,0 +1,239 @@
+use crate::actor::BaseActor;
+use crate::data::event::GameEvent;
+use crate::frontend::GameFrontend;
+use crate::game::beliefs::ProbePoint;
+use crate::game::setup::GameSetup;
+use crate::game::{DayNightCount, EndResult, Game, GameRng, GameState};
+use crate::prompts::PromptOverrides;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// Bump whenever [`SaveFile`] or anything it contains changes shape
+pub const SAVE_VERSION: u32 = 1;
+
+/// Everything needed to continue a game from the start of a phase
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub game_id: String,
+    /// Set when this game was forked off another one
+    #[serde(default)]
+    pub parent: Option<ParentLink>,
+    pub seed: u64,
+    pub rng: GameRng,
+    pub playable_actor: Option<u8>,
+    pub last_kill: Option<Vec<u8>>,
+    pub day_night_count: DayNightCount,
+    pub end_result: Option<EndResult>,
+    pub state: GameState,
+    pub checkpoints: Vec<PhaseCheckpoint>,
+    pub prompts: PromptOverrides,
+    pub setup: GameSetup,
+    pub belief_probes: Vec<ProbePoint>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ParentLink {
+    pub game_id: String,
+    /// The context entry the fork was requested at
+    pub context_index: usize,
+}
+
+/// The state at the start of a phase. The context and events are shared with the save, only their lengths are kept.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PhaseCheckpoint {
+    pub context_len: usize,
+    pub events_len: usize,
+    pub rng: GameRng,
+    pub last_kill: Option<Vec<u8>>,
+    pub day_night_count: DayNightCount,
+    pub actors: Vec<BaseActor>,
+}
+
+pub fn new_game_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    /// Only saves with phase checkpoints can be forked
+    NoPhaseHistory,
+    /// An actor has a role the save doesn't define
+    UnknownRole(String),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "Could not access the save file: {}", err),
+            SaveError::Json(err) => write!(f, "The save file is malformed: {}", err),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "The save file has version {}, only versions up to {} are supported",
+                version, SAVE_VERSION
+            ),
+            SaveError::NoPhaseHistory => write!(
+                f,
+                "The save has no phase checkpoints, its game never started a phase"
+            ),
+            SaveError::UnknownRole(err) => write!(f, "The save can't be played: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl SaveFile {
+    pub fn read(path: &Path) -> Result<Self, SaveError> {
+        let text = std::fs::read_to_string(path).map_err(SaveError::Io)?;
+        let save: SaveFile = serde_json::from_str(&text).map_err(SaveError::Json)?;
+        if !(1..=SAVE_VERSION).contains(&save.version) {
+            return Err(SaveError::UnsupportedVersion(save.version));
+        }
+        let actors = save.state.actors.iter().chain(
+            save.checkpoints
+                .iter()
+                .flat_map(|checkpoint| &checkpoint.actors),
+        );
+        save.state
+            .roles
+            .check(actors.map(|actor| &actor.role))
+            .map_err(SaveError::UnknownRole)?;
+        Ok(save)
+    }
+
+    /// Saves taken during the final phase only know the result from their events
+    pub fn final_result(&self) -> Option<EndResult> {
+        self.end_result.clone().or_else(|| {
+            self.state.events.iter().find_map(|event| match event {
+                GameEvent::GameEnded { result } => Some(result.clone()),
+                _ => None,
+            })
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(SaveError::Io)?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(SaveError::Json)?;
+        std::fs::write(path, text).map_err(SaveError::Io)
+    }
+}
+
+impl Game {
+    pub fn to_save(&self) -> SaveFile {
+        SaveFile {
+            version: SAVE_VERSION,
+            game_id: self.id.clone(),
+            parent: self.parent.clone(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            playable_actor: self.playable_actor,
+            last_kill: self.last_kill.clone(),
+            day_night_count: self.day_night_count.clone(),
+            end_result: self.end_result.clone(),
+            state: self.state.clone(),
+            checkpoints: self.checkpoints.clone(),
+            prompts: self.prompts.clone(),
+            setup: self.setup.clone(),
+            belief_probes: self.belief_probes.clone(),
+        }
+    }
+
+    pub fn from_save(frontend: Box<dyn GameFrontend>, save: SaveFile) -> Self {
+        let mut game = Game::new(frontend, save.playable_actor, save.seed);
+        game.id = save.game_id;
+        game.parent = save.parent;
+        game.checkpoints = save.checkpoints;
+        game.prompts = save.prompts;
+        game.setup = save.setup;
+        game.belief_probes = save.belief_probes;
+        game.rng = save.rng;
+        game.last_kill = save.last_kill;
+        game.day_night_count = save.day_night_count;
+        game.end_result = save.end_result;
+        game.state = save.state;
+        tracing::info!(game = %game.id, seed = game.seed, "Resumed game");
+        game.frontend.actors_initialized(&game.state);
+        game.frontend.actor_list_changed(&game.state);
+        if let Some(entry) = game.state.context.last() {
+            game.frontend.context_added(&game.state, entry);
+        }
+        game
+    }
+
+    /// Called at every phase boundary, which is the point a save resumes from
+    pub fn checkpoint(&mut self) {
+        let checkpoint = PhaseCheckpoint {
+            context_len: self.state.context.len(),
+            events_len: self.state.events.len(),
+            rng: self.rng.clone(),
+            last_kill: self.last_kill.clone(),
+            day_night_count: self.day_night_count.clone(),
+            actors: self.state.actors.clone(),
+        };
+        // A resumed game passes through the checkpoint it was saved at a second time
+        if self
+            .checkpoints
+            .last()
+            .is_some_and(|last| last.context_len == checkpoint.context_len)
+        {
+            self.checkpoints.pop();
+        }
+        self.checkpoints.push(checkpoint);
+        let save = self.to_save();
+        self.write_autosave(&save);
+        self.frontend.checkpoint(&save);
+    }
+
+    pub(crate) fn write_autosave(&self, save: &SaveFile) {
+        if let Some(autosave) = &self.autosave
+            && let Err(err) = autosave.write(save)
+        {
+            tracing::warn!("Failed to autosave: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SAVE_VERSION, SaveError, SaveFile};
+    use crate::data::roles::GameRole;
+    use crate::game::night::tests::game_with;
+    use std::path::PathBuf;
+
+    fn save_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("save-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn reads_back_what_it_wrote() {
+        let path = save_path("current");
+        let game = game_with(&[GameRole::MAFIOSO, GameRole::DOCTOR, GameRole::VILLAGER]);
+        game.to_save().write(&path).unwrap();
+        let save = SaveFile::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.game_id, game.id);
+        assert_eq!(save.state.actors.len(), 3);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let path = save_path("newer");
+        let mut save = game_with(&[GameRole::MAFIOSO, GameRole::VILLAGER]).to_save();
+        save.version = SAVE_VERSION + 1;
+        save.write(&path).unwrap();
+        let result = SaveFile::read(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(SaveError::UnsupportedVersion(version)) if version == SAVE_VERSION + 1
+        ));
+    }
+}
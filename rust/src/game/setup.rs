@@ -0,0 +1,421 @@
+use crate::data::roles::{GameRole, NightActionKind, RoleAlignment, RoleBook, RoleDefinition};
+use crate::game::init::default_role_pool;
+use crate::game::{EXTRA_MESSAGES, GameRng};
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Which roles are dealt and how many messages every discussion has
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameSetup {
+    pub name: String,
+    /// Dealt in order to the first actors after shuffling, everyone past the list is a `Villager`
+    pub roles: Vec<RoleSlot>,
+    /// Adds Mafioso slots for larger lobbies
+    #[serde(default)]
+    pub mafia_scaling: Option<MafiaScaling>,
+    /// Spent before the core messages, which are one per player in every discussion
+    #[serde(default = "default_extra_messages")]
+    pub extra_messages: u8,
+    /// Whether investigating a neutral killer, like the Serial Killer, finds Mafia
+    #[serde(default)]
+    pub neutral_killers_suspicious: bool,
+}
+
+fn default_extra_messages() -> u8 {
+    EXTRA_MESSAGES
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        Self {
+            name: String::from("Classic"),
+            roles: default_role_pool()
+                .into_iter()
+                .map(RoleSlot::Fixed)
+                .collect(),
+            mafia_scaling: None,
+            extra_messages: EXTRA_MESSAGES,
+            neutral_killers_suspicious: false,
+        }
+    }
+}
+
+/// A seat of a setup, either a given role or one drawn when the game is dealt
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RoleSlot {
+    /// Tried first, since any name would parse as a role
+    Random(RoleCategory),
+    Fixed(GameRole),
+}
+
+/// The defined roles a [`RoleSlot::Random`] is drawn from, by alignment and night action
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RoleCategory {
+    RandomTown,
+    TownInvestigative,
+    TownProtective,
+    TownKilling,
+    TownSupport,
+    RandomMafia,
+    RandomNeutral,
+    NeutralKilling,
+}
+
+/// One Mafia member for every `players_per_mafia` players, rounded down, but never fewer than `min`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MafiaScaling {
+    pub players_per_mafia: u8,
+    #[serde(default = "default_min_mafia")]
+    pub min: u8,
+}
+
+fn default_min_mafia() -> u8 {
+    1
+}
+
+impl RoleCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RoleCategory::RandomTown => "Random Town",
+            RoleCategory::TownInvestigative => "Town Investigative",
+            RoleCategory::TownProtective => "Town Protective",
+            RoleCategory::TownKilling => "Town Killing",
+            RoleCategory::TownSupport => "Town Support",
+            RoleCategory::RandomMafia => "Random Mafia",
+            RoleCategory::RandomNeutral => "Random Neutral",
+            RoleCategory::NeutralKilling => "Neutral Killing",
+        }
+    }
+
+    pub fn alignment(&self) -> RoleAlignment {
+        match self {
+            RoleCategory::RandomMafia => RoleAlignment::Mafia,
+            RoleCategory::RandomNeutral | RoleCategory::NeutralKilling => RoleAlignment::Neutral,
+            _ => RoleAlignment::Town,
+        }
+    }
+
+    pub fn contains(&self, definition: &RoleDefinition) -> bool {
+        if definition.alignment != self.alignment() {
+            return false;
+        }
+        let kind = definition.night_action.as_ref().map(|action| action.kind);
+        match self {
+            RoleCategory::RandomTown | RoleCategory::RandomMafia | RoleCategory::RandomNeutral => {
+                true
+            }
+            RoleCategory::TownInvestigative => kind == Some(NightActionKind::Investigate),
+            RoleCategory::TownProtective => kind == Some(NightActionKind::Protect),
+            RoleCategory::TownKilling | RoleCategory::NeutralKilling => {
+                kind == Some(NightActionKind::Kill)
+            }
+            RoleCategory::TownSupport => kind == Some(NightActionKind::Block),
+        }
+    }
+}
+
+impl RoleSlot {
+    pub fn name(&self) -> String {
+        match self {
+            RoleSlot::Fixed(role) => role.name(),
+            RoleSlot::Random(category) => category.name().to_string(),
+        }
+    }
+
+    /// `None` for a fixed role that isn't in `roles`
+    pub fn alignment(&self, roles: &RoleBook) -> Option<RoleAlignment> {
+        match self {
+            RoleSlot::Fixed(role) => roles
+                .get(role)
+                .map(|definition| definition.alignment.clone()),
+            RoleSlot::Random(category) => Some(category.alignment()),
+        }
+    }
+
+    /// Only draws from `rng` for random slots, so fixed setups deal the same for a seed
+    pub fn deal(&self, roles: &RoleBook, rng: &mut GameRng) -> GameRole {
+        match self {
+            RoleSlot::Fixed(role) => role.clone(),
+            RoleSlot::Random(category) => roles
+                .definitions()
+                .filter(|definition| category.contains(definition))
+                .collect::<Vec<_>>()
+                .choose(rng)
+                .map(|definition| definition.role())
+                .unwrap_or(GameRole::VILLAGER),
+        }
+    }
+}
+
+impl MafiaScaling {
+    pub fn mafia_for(&self, player_count: usize) -> usize {
+        (player_count / self.players_per_mafia.max(1) as usize).max(self.min as usize)
+    }
+}
+
+/// The setups that are always on offer, ahead of the saved presets
+pub fn builtin_presets() -> Vec<GameSetup> {
+    vec![
+        GameSetup::default(),
+        GameSetup {
+            name: String::from("Scaling"),
+            roles: vec![
+                RoleSlot::Random(RoleCategory::TownProtective),
+                RoleSlot::Random(RoleCategory::TownInvestigative),
+                RoleSlot::Random(RoleCategory::RandomTown),
+            ],
+            mafia_scaling: Some(MafiaScaling {
+                players_per_mafia: 4,
+                min: 1,
+            }),
+            extra_messages: EXTRA_MESSAGES,
+            neutral_killers_suspicious: false,
+        },
+    ]
+}
+
+impl GameSetup {
+    /// The slots for this many players, with the Mafioso slots [`GameSetup::mafia_scaling`] adds at the end
+    pub fn slots(&self, player_count: usize, roles: &RoleBook) -> Vec<RoleSlot> {
+        let mut slots = self.roles.clone();
+        if let Some(scaling) = &self.mafia_scaling {
+            let mafia = slots
+                .iter()
+                .filter(|slot| slot.alignment(roles) == Some(RoleAlignment::Mafia))
+                .count();
+            for _ in mafia..scaling.mafia_for(player_count) {
+                slots.push(RoleSlot::Fixed(GameRole::MAFIOSO));
+            }
+        }
+        slots
+    }
+
+    pub fn deal(&self, player_count: usize, roles: &RoleBook, rng: &mut GameRng) -> Vec<GameRole> {
+        self.slots(player_count, roles)
+            .iter()
+            .map(|slot| slot.deal(roles, rng))
+            .collect()
+    }
+
+    /// The slots and scaling, as listed in the setup picker
+    pub fn describe(&self) -> String {
+        let mut description = self
+            .roles
+            .iter()
+            .map(|slot| slot.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Some(scaling) = &self.mafia_scaling {
+            if !description.is_empty() {
+                description.push_str(", ");
+            }
+            description.push_str(&format!(
+                "1 Mafia per {} players, at least {}",
+                scaling.players_per_mafia, scaling.min
+            ));
+        }
+        if self.neutral_killers_suspicious {
+            description.push_str(", neutral killers look suspicious");
+        }
+        description
+    }
+
+    /// The alignment investigating a player of `definition` finds. The role's own `appears_as` comes first.
+    pub fn investigated_alignment(&self, definition: &RoleDefinition) -> RoleAlignment {
+        match &definition.appears_as {
+            Some(alignment) => alignment.clone(),
+            None if self.neutral_killers_suspicious
+                && RoleCategory::NeutralKilling.contains(definition) =>
+            {
+                RoleAlignment::Mafia
+            }
+            None => definition.alignment.clone(),
+        }
+    }
+
+    /// Checks that a game with this many players can be dealt from `roles` and doesn't end before it starts
+    pub fn validate(&self, player_count: usize, roles: &RoleBook) -> Result<(), String> {
+        if let Some(scaling) = &self.mafia_scaling
+            && scaling.players_per_mafia == 0
+        {
+            return Err(format!("{} scales the Mafia by 0 players", self.name));
+        }
+        let slots = self.slots(player_count, roles);
+        if slots.len() > player_count {
+            return Err(format!(
+                "{} deals {} roles, but there are only {} players",
+                self.name,
+                slots.len(),
+                player_count
+            ));
+        }
+        for slot in &self.roles {
+            match slot {
+                RoleSlot::Fixed(role) if !roles.contains(role) => {
+                    return Err(format!(
+                        "{} deals {}, which isn't defined",
+                        self.name,
+                        role.name()
+                    ));
+                }
+                RoleSlot::Random(category)
+                    if !roles
+                        .definitions()
+                        .any(|definition| category.contains(definition)) =>
+                {
+                    return Err(format!(
+                        "{} draws from {}, which has no roles",
+                        self.name,
+                        category.name()
+                    ));
+                }
+                _ => {}
+            }
+        }
+        let mafia = slots
+            .iter()
+            .filter(|slot| slot.alignment(roles) == Some(RoleAlignment::Mafia))
+            .count();
+        if mafia == 0 {
+            return Err(format!("{} has no Mafia", self.name));
+        }
+        if mafia * 2 >= player_count {
+            return Err(format!(
+                "{} has {} Mafia, which is already parity with {} players",
+                self.name, mafia, player_count
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A missing file has no presets
+pub fn read_presets(path: &Path) -> std::io::Result<Vec<GameSetup>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(std::io::Error::other),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn write_presets(presets: &[GameSetup], path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(presets).unwrap())
+}
+
+/// Adds `presets` to `existing`, replacing the ones with the same name
+pub fn merge_presets(existing: &mut Vec<GameSetup>, presets: Vec<GameSetup>) {
+    for preset in presets {
+        match existing.iter_mut().find(|setup| setup.name == preset.name) {
+            Some(setup) => *setup = preset,
+            None => existing.push(preset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameSetup, MafiaScaling, RoleCategory, RoleSlot};
+    use crate::data::roles::{GameRole, RoleBook};
+    use std::borrow::Cow;
+
+    fn setup(roles: Vec<RoleSlot>, mafia_scaling: Option<MafiaScaling>) -> GameSetup {
+        GameSetup {
+            name: String::from("Test"),
+            roles,
+            mafia_scaling,
+            ..GameSetup::default()
+        }
+    }
+
+    #[test]
+    fn role_names_are_not_read_as_categories() {
+        let slots: Vec<RoleSlot> =
+            serde_json::from_str(r#"["Doctor", "Serial Killer", "TownProtective"]"#).unwrap();
+        assert!(
+            slots
+                == vec![
+                    RoleSlot::Fixed(GameRole::DOCTOR),
+                    RoleSlot::Fixed(GameRole(Cow::Borrowed("Serial Killer"))),
+                    RoleSlot::Random(RoleCategory::TownProtective),
+                ]
+        );
+    }
+
+    #[test]
+    fn scales_the_mafia_with_the_players() {
+        let scaling = MafiaScaling {
+            players_per_mafia: 4,
+            min: 1,
+        };
+        assert_eq!(scaling.mafia_for(3), 1);
+        assert_eq!(scaling.mafia_for(8), 2);
+        assert_eq!(scaling.mafia_for(11), 2);
+        let slots = setup(vec![RoleSlot::Fixed(GameRole::MAFIOSO)], Some(scaling))
+            .slots(12, &RoleBook::default());
+        let mafiosi = slots
+            .iter()
+            .filter(|slot| **slot == RoleSlot::Fixed(GameRole::MAFIOSO))
+            .count();
+        assert_eq!(mafiosi, 3);
+    }
+
+    #[test]
+    fn rejects_setups_without_mafia() {
+        let setup = setup(
+            vec![
+                RoleSlot::Fixed(GameRole::DOCTOR),
+                RoleSlot::Fixed(GameRole::SHERIFF),
+            ],
+            None,
+        );
+        assert_eq!(
+            setup.validate(6, &RoleBook::default()),
+            Err(String::from("Test has no Mafia"))
+        );
+    }
+
+    #[test]
+    fn rejects_mafia_at_parity() {
+        let setup = setup(
+            vec![
+                RoleSlot::Fixed(GameRole::MAFIOSO),
+                RoleSlot::Fixed(GameRole::MAFIOSO),
+            ],
+            None,
+        );
+        assert_eq!(
+            setup.validate(4, &RoleBook::default()),
+            Err(String::from(
+                "Test has 2 Mafia, which is already parity with 4 players"
+            ))
+        );
+        assert_eq!(setup.validate(5, &RoleBook::default()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_scaling_by_zero_players() {
+        let setup = setup(
+            vec![RoleSlot::Fixed(GameRole::DOCTOR)],
+            Some(MafiaScaling {
+                players_per_mafia: 0,
+                min: 1,
+            }),
+        );
+        assert_eq!(
+            setup.validate(8, &RoleBook::default()),
+            Err(String::from("Test scales the Mafia by 0 players"))
+        );
+    }
+
+    #[test]
+    fn builtin_presets_are_valid() {
+        for preset in super::builtin_presets() {
+            assert_eq!(preset.validate(8, &RoleBook::default()), Ok(()));
+        }
+    }
+}
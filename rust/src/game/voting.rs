@@ -1,71 +1,67 @@
-use crate::actor::BaseActor;
-use crate::chat::ChatCommand;
 use crate::data::action::Action;
 use crate::data::context_entry::{ContextEntry, SayerType};
-use crate::data::extra_data::ExtraData;
+use crate::data::event::GameEvent;
+use crate::data::extra_data::{ExtraData, channel_of};
 use crate::game::Game;
-use crate::llm::tools::Tool;
+use crate::llm::tools::voting_tools;
 use crate::prompts::general::{actor_voted, time_to_vote};
 use std::collections::HashMap;
 
 impl Game {
-    pub async fn handle_voting(
-        &mut self,
-        actors: &[&BaseActor],
-        extra_data: &[ExtraData],
-    ) -> Option<u8> {
+    pub async fn handle_voting(&mut self, actors: &[u8], extra_data: &[ExtraData]) -> Option<u8> {
         let mut votes = Vec::new();
-        for actor in actors {
-            let pick = actor
-                .prompt(
-                    time_to_vote(),
-                    self,
-                    &[
-                        crate::llm::tools::Abstain::make_tool(),
-                        crate::llm::tools::Talk::make_tool(),
-                        crate::llm::tools::ProvideID::make_tool(),
-                        crate::llm::tools::MultiCall::make_tool(),
-                    ],
-                )
+        for &actor_id in actors {
+            let pick = self
+                .prompt_actor(actor_id, &time_to_vote(&self.prompts), &voting_tools())
                 .await;
             let mut comment: Option<String> = None;
-            let mut target_vote: Option<&BaseActor> = None;
+            let mut target_vote: Option<u8> = None;
             match pick {
                 Action::MultiCall(actions) => {
                     for action in actions {
                         if let Action::Talk(message) = action {
                             comment = Some(message);
                         } else if let Action::ProvideID(id) = action {
-                            target_vote = Some(Self::get_actor_from_id(id).unwrap());
+                            target_vote = Some(id);
                             votes.push(id);
                         }
                     }
                 }
                 Action::ProvideID(id) => {
-                    target_vote = Some(Self::get_actor_from_id(id).unwrap());
+                    target_vote = Some(id);
                     votes.push(id);
                 }
                 _ => {}
             }
-            let text = actor_voted(actor, target_vote, comment);
+            let text = actor_voted(
+                self.state.get_actor_from_id(actor_id).unwrap(),
+                target_vote.map(|id| self.state.get_actor_from_id(id).unwrap()),
+                comment.clone(),
+            );
             self.add_to_context(ContextEntry {
                 content: text.clone(),
                 sayer_type: SayerType::System,
                 extra_data: extra_data.to_vec(),
             });
+            self.emit(GameEvent::Voted {
+                voter: actor_id,
+                channel: channel_of(extra_data),
+                target: target_vote,
+                comment,
+            });
             if !self.day_night_count.is_night
                 || self.playable_actor.is_some()
-                    && matches!(
-                        Self::get_actor_from_id(self.playable_actor.unwrap())
-                            .unwrap()
-                            .role
-                            .alignment(),
-                        crate::data::roles::RoleAlignment::Mafia
-                    )
+                    && self
+                        .state
+                        .role_of(
+                            self.state
+                                .get_actor_from_id(self.playable_actor.unwrap())
+                                .unwrap(),
+                        )
+                        .alignment
+                        == crate::data::roles::RoleAlignment::Mafia
             {
-                self.command_sender
-                    .send(ChatCommand::CameraFocus(actor.id, text))
-                    .unwrap();
+                self.frontend.focus_actor(actor_id, text);
             }
         }
         Self::get_voted_out(&votes)
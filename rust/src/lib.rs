@@ -1,11 +1,20 @@
-mod actor;
+pub mod actor;
+pub mod balance;
+pub mod benchmark;
 mod chat;
 mod configuration;
-mod data;
-mod game;
-mod llm;
-mod prompts;
+pub mod data;
+pub mod experiment;
+pub mod export;
+pub mod frontend;
+pub mod game;
+pub mod llm;
+pub mod logging;
+pub mod prompts;
+pub mod scripted;
+pub mod simulation;
 mod tokio;
+pub mod tournament;
 
 use godot::prelude::*;
 
@@ -14,6 +23,10 @@ struct MyExtension;
 #[gdextension]
 unsafe impl ExtensionLibrary for MyExtension {}
 
+pub fn load_configuration_scene() -> Gd<PackedScene> {
+    load::<PackedScene>("res://scenes/configuration.tscn")
+}
+
 pub fn load_world_scene() -> Gd<PackedScene> {
     load::<PackedScene>("res://scenes/world.tscn")
 }
@@ -25,3 +38,80 @@ pub fn load_message_scene() -> Gd<PackedScene> {
 pub fn load_model_scene() -> Gd<PackedScene> {
     load::<PackedScene>("res://models/model.tscn")
 }
+
+pub fn saves_directory() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://saves")
+        .to_string()
+        .into()
+}
+
+pub fn transcripts_directory() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://transcripts")
+        .to_string()
+        .into()
+}
+
+pub fn analytics_directory() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://analytics")
+        .to_string()
+        .into()
+}
+
+pub fn cassettes_directory() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://cassettes")
+        .to_string()
+        .into()
+}
+
+pub fn logs_directory() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://logs")
+        .to_string()
+        .into()
+}
+
+pub fn autosaves_directory() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://autosaves")
+        .to_string()
+        .into()
+}
+
+/// Extra role definitions, one JSON list of roles per file
+pub fn roles_directory() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://roles")
+        .to_string()
+        .into()
+}
+
+/// Forwards log lines to Godot's output
+struct GodotConsole;
+
+impl std::io::Write for GodotConsole {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        godot_print!("{}", String::from_utf8_lossy(buf).trim_end());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Logs to Godot's output and to `user://logs`, does nothing after the first call
+pub fn init_logging() {
+    logging::init(&logs_directory(), || GodotConsole, "info");
+}
+
+/// Role setups the configuration screen offers next to the classic one
+pub fn presets_path() -> std::path::PathBuf {
+    godot::classes::ProjectSettings::singleton()
+        .globalize_path("user://presets.json")
+        .to_string()
+        .into()
+}
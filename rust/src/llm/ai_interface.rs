@@ -1,132 +1,327 @@
+use crate::actor::PromptReply;
 use crate::data::action::Action;
 use crate::data::channel::Channel;
 use crate::data::context_entry::{ContextEntry, SayerType};
 use crate::data::extra_data::ExtraData;
-use crate::game::Game;
-use crate::llm::OpenRouterResponse;
-use crate::llm::tools::{MultiCall, ProvideID, TagPlayerForComment, Talk, Whisper};
+use crate::game::{Game, GameState};
+use crate::llm::tools::{
+    MultiCall, ProvideID, ReportSuspicions, TagPlayerForComment, Talk, Tool, Whisper, tool_name,
+};
+use crate::llm::{OpenRouterChoice, OpenRouterResponse};
 use async_openai::Client;
 use async_openai::config::OpenAIConfig;
+use async_openai::error::OpenAIError;
 use async_openai::types::chat::{
-    ChatCompletionRequestSystemMessage, ChatCompletionTools, CreateChatCompletionRequestArgs,
-    ReasoningEffort,
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage, ChatCompletionTools,
+    CreateChatCompletionRequestArgs, FunctionCall, ReasoningEffort,
 };
-use std::sync::OnceLock;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+use std::time::Instant;
+
+static CLIENT: RwLock<Option<Client<OpenAIConfig>>> = RwLock::new(None);
 
-pub static CLIENT: OnceLock<Client<OpenAIConfig>> = OnceLock::new();
+/// Points every LLM actor at `api_base`, replacing the endpoint of any earlier game
+pub fn set_client(api_base: &str, api_key: &str) {
+    let client = Client::with_config(
+        OpenAIConfig::default()
+            .with_api_base(api_base)
+            .with_api_key(api_key),
+    );
+    *CLIENT.write().unwrap() = Some(client);
+}
 
+fn client() -> Option<Client<OpenAIConfig>> {
+    CLIENT.read().unwrap().clone()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AIInterface {
     pub model_id: String,
     pub owner_id: u8,
+    /// Extra standing instructions for this actor, sent before every prompt
+    #[serde(default)]
+    pub instructions: Option<String>,
+    /// Defaults to minimal reasoning when unset
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
 }
 
 impl AIInterface {
-    pub async fn send_request_with_tools(
+    /// Everything this actor is sent for `prompt`, given the `context` so far
+    pub fn build_messages(
         &self,
+        state: &GameState,
+        context: &[ContextEntry],
         prompt: &str,
-        tools: &[ChatCompletionTools],
-    ) -> Action {
-        let mut messages: Vec<_> = Game::get_context()
+    ) -> Vec<ChatCompletionRequestMessage> {
+        let mut messages: Vec<_> = context
             .iter()
-            .filter_map(|entry| entry.to_chat_message(self.owner_id))
+            .filter_map(|entry| entry.to_chat_message(state, self.owner_id))
             .collect();
+        if let Some(instructions) = &self.instructions {
+            messages.push(ChatCompletionRequestSystemMessage::from(instructions.as_str()).into());
+        }
         messages.push(ChatCompletionRequestSystemMessage::from(prompt).into());
-        let client = CLIENT.get().unwrap();
+        messages
+    }
+
+    /// Sends `prompt` with the game's context, or answers it from the cassette
+    #[tracing::instrument(
+        name = "llm_request",
+        skip_all,
+        fields(
+            model = %self.model_id,
+            actor_id = self.owner_id,
+            tools = ?tools.iter().filter_map(tool_name).collect::<Vec<_>>(),
+        )
+    )]
+    async fn request_choice(
+        &self,
+        prompt: &str,
+        game: &Game,
+        tools: &[ChatCompletionTools],
+    ) -> Result<OpenRouterChoice, LlmError> {
+        let messages = self.build_messages(&game.state, &game.state.context, prompt);
         let request = CreateChatCompletionRequestArgs::default()
             .messages(messages)
             .tools(tools)
             .model(&self.model_id)
-            .reasoning_effort(ReasoningEffort::Minimal)
+            .reasoning_effort(
+                self.reasoning_effort
+                    .clone()
+                    .unwrap_or(ReasoningEffort::Minimal),
+            )
             .build()
             .unwrap();
-        let response: OpenRouterResponse = client.chat().create_byot(&request).await.unwrap();
+        let started = Instant::now();
+        let raw_response = match &game.cassette {
+            Some(cassette) if cassette.is_replaying() => {
+                tracing::debug!("Replaying from the cassette");
+                cassette
+                    .take_response(&request)
+                    .ok_or(LlmError::NotOnCassette)?
+            }
+            cassette => {
+                let client = client().ok_or(LlmError::NoClient)?;
+                let raw_response: serde_json::Value = client
+                    .chat()
+                    .create_byot(&request)
+                    .await
+                    .map_err(LlmError::Request)?;
+                if let Some(cassette) = cassette
+                    && let Err(err) = cassette.store(&request, &raw_response)
+                {
+                    tracing::warn!("Failed to record to the cassette: {}", err);
+                }
+                raw_response
+            }
+        };
+        let response: OpenRouterResponse =
+            serde_json::from_value(raw_response).map_err(LlmError::Response)?;
+        let usage = response.usage.unwrap_or_default();
+        let tool_calls = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.tool_calls.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|tool_call| tool_call.function.name.as_str())
+            .collect::<Vec<_>>();
+        tracing::debug!(
+            latency_ms = started.elapsed().as_millis() as u64,
+            prompt_tokens = usage.prompt_tokens,
+            completion_tokens = usage.completion_tokens,
+            ?tool_calls,
+            "Response received"
+        );
+        response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or(LlmError::NoChoices)
+    }
 
-        if let Some(choice) = response.choices.first() {
-            let mut collected_actions = Vec::new();
+    pub async fn send_request_with_tools(
+        &self,
+        prompt: &str,
+        game: &Game,
+        tools: &[ChatCompletionTools],
+    ) -> Result<PromptReply, LlmError> {
+        let choice = self.request_choice(prompt, game, tools).await?;
+        let mut collected_actions = Vec::new();
+        let mut raw_entry = None;
 
+        {
+            let mut context_entry: ContextEntry = ContextEntry {
+                content: String::from("NONE"),
+                sayer_type: SayerType::Actor(self.owner_id),
+                extra_data: vec![ExtraData::SaidInChannel(Channel::Raw(self.owner_id))],
+            };
+            if let Some(content) = &choice.message.content
+                && !content.is_empty()
             {
-                let mut context_entry: ContextEntry = ContextEntry {
-                    content: String::from("NONE"),
-                    sayer_type: SayerType::Actor(self.owner_id),
-                    extra_data: vec![ExtraData::SaidInChannel(Channel::Raw(self.owner_id))],
-                };
-                if let Some(content) = &choice.message.content
-                    && !content.is_empty()
-                {
-                    context_entry.content = format!("CONTENT:\n{}\n", content.trim());
-                }
-                if let Some(reasoning) = &choice.message.reasoning
-                    && !reasoning.is_empty()
-                {
-                    context_entry.content = format!("REASONING:\n{}\n", reasoning.trim(),);
-                }
-                context_entry.content = context_entry.content.trim().to_string();
-                if context_entry.content != "NONE" {
-                    Game::get_context_mut().push(context_entry);
-                }
+                context_entry.content = format!("CONTENT:\n{}\n", content.trim());
+            }
+            if let Some(reasoning) = &choice.message.reasoning
+                && !reasoning.is_empty()
+            {
+                context_entry.content = format!("REASONING:\n{}\n", reasoning.trim(),);
+            }
+            context_entry.content = context_entry.content.trim().to_string();
+            if context_entry.content != "NONE" {
+                raw_entry = Some(context_entry);
             }
+        }
 
-            return if let Some(tool_calls) = &choice.message.tool_calls {
-                for tool_call in tool_calls {
-                    handle_tool_call(
-                        (
-                            &tool_call.function.name,
-                            tool_call.function.arguments.clone(),
-                        ),
-                        &mut collected_actions,
-                    );
-                }
+        let mut recorded_calls = Vec::new();
+        let action = if let Some(tool_calls) = &choice.message.tool_calls {
+            for tool_call in tool_calls {
+                recorded_calls.push(FunctionCall {
+                    name: tool_call.function.name.clone(),
+                    arguments: tool_call.function.arguments.clone(),
+                });
+                handle_tool_call(
+                    (
+                        &tool_call.function.name,
+                        tool_call.function.arguments.clone(),
+                    ),
+                    &mut collected_actions,
+                )?;
+            }
 
-                if collected_actions.len() == 1 {
-                    collected_actions.into_iter().next().unwrap()
-                } else {
-                    Action::MultiCall(collected_actions)
-                }
+            if collected_actions.len() == 1 {
+                collected_actions.into_iter().next().unwrap()
             } else {
-                godot::global::godot_warn!("No tool was used, abstaining");
-                Action::Abstain
-            };
+                Action::MultiCall(collected_actions)
+            }
+        } else {
+            tracing::warn!("No tool was used, abstaining");
+            Action::Abstain
+        };
+        Ok(PromptReply {
+            action,
+            raw: raw_entry,
+            tool_calls: recorded_calls,
+        })
+    }
+
+    /// Asks `prompt` with only [`ReportSuspicions`] on offer. Unlike a normal prompt,
+    /// nothing of the reply is added to the context.
+    pub async fn probe_beliefs(&self, prompt: &str, game: &Game) -> Option<ReportSuspicions> {
+        let choice = match self
+            .request_choice(prompt, game, &[ReportSuspicions::make_tool()])
+            .await
+        {
+            Ok(choice) => choice,
+            Err(err) => {
+                tracing::warn!("The belief probe of {} failed: {}", self.model_id, err);
+                return None;
+            }
+        };
+        let Some(tool_call) = choice
+            .message
+            .tool_calls
+            .into_iter()
+            .flatten()
+            .find(|tool_call| tool_call.function.name == "ReportSuspicions")
+        else {
+            tracing::warn!("{} did not answer the belief probe", self.model_id);
+            return None;
+        };
+        match serde_json::from_str(&tool_call.function.arguments) {
+            Ok(report) => Some(report),
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to parse the belief probe answer of {}: {}",
+                    self.model_id,
+                    err
+                );
+                None
+            }
         }
+    }
+}
+
+/// Why a model's turn could not be turned into an [`Action`]
+pub enum LlmError {
+    Request(OpenAIError),
+    Response(serde_json::Error),
+    NoChoices,
+    NotOnCassette,
+    NoClient,
+    UnknownTool(String),
+    BadArguments {
+        tool: String,
+        error: serde_json::Error,
+    },
+}
 
-        unreachable!();
+impl Display for LlmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LlmError::Request(err) => write!(f, "The request failed: {}", err),
+            LlmError::Response(err) => write!(f, "The response is malformed: {}", err),
+            LlmError::NoChoices => write!(f, "The response has no choices"),
+            LlmError::NotOnCassette => {
+                write!(f, "The cassette has no recorded response for this request")
+            }
+            LlmError::NoClient => write!(f, "No API endpoint has been set"),
+            LlmError::UnknownTool(tool) => write!(f, "The model called an unknown tool {}", tool),
+            LlmError::BadArguments { tool, error } => {
+                write!(f, "The model called {} with bad arguments: {}", tool, error)
+            }
+        }
     }
 }
 
-fn handle_tool_call(tool_call: (&str, String), collected_actions: &mut Vec<Action>) {
-    match tool_call.0 {
+fn parse_arguments<T: DeserializeOwned>(tool: &str, arguments: &str) -> Result<T, LlmError> {
+    serde_json::from_str(arguments).map_err(|error| LlmError::BadArguments {
+        tool: tool.to_string(),
+        error,
+    })
+}
+
+fn handle_tool_call(
+    tool_call: (&str, String),
+    collected_actions: &mut Vec<Action>,
+) -> Result<(), LlmError> {
+    let (tool, arguments) = tool_call;
+    match tool {
         "Abstain" => collected_actions.push(Action::Abstain),
         "Whisper" => {
-            let whisper = serde_json::from_str::<Whisper>(&tool_call.1).unwrap();
+            let whisper = parse_arguments::<Whisper>(tool, &arguments)?;
             collected_actions.push(Action::Whisper(whisper.to, whisper.message));
         }
         "TagPlayerForComment" => {
             collected_actions.push(Action::TagPlayerForComment(
-                serde_json::from_str::<TagPlayerForComment>(&tool_call.1)
-                    .unwrap()
-                    .id,
+                parse_arguments::<TagPlayerForComment>(tool, &arguments)?.id,
             ));
         }
         "ProvideID" => collected_actions.push(Action::ProvideID(
-            serde_json::from_str::<ProvideID>(&tool_call.1).unwrap().id,
+            parse_arguments::<ProvideID>(tool, &arguments)?.id,
         )),
         "Talk" => collected_actions.push(Action::Talk(
-            serde_json::from_str::<Talk>(&tool_call.1).unwrap().message,
+            parse_arguments::<Talk>(tool, &arguments)?.message,
         )),
         "MultiCall" => {
-            let multi_call = match serde_json::from_str::<MultiCall>(&tool_call.1) {
+            let multi_call = match serde_json::from_str::<MultiCall>(&arguments) {
                 Ok(multi_call) => multi_call,
                 Err(err) => {
-                    godot::global::godot_warn!("Failed to parse MultiCall: {}", err);
-                    return;
+                    tracing::warn!("Failed to parse MultiCall: {}", err);
+                    return Ok(());
                 }
             };
             for action in &multi_call.actions {
                 handle_tool_call(
                     (&action.tool, action.arguments.to_string()),
                     collected_actions,
-                );
+                )?;
             }
         }
-        _ => panic!("Unknown tool call"),
+        _ => return Err(LlmError::UnknownTool(tool.to_string())),
     }
+    Ok(())
 }
@@ -0,0 +1,141 @@
+use async_openai::types::chat::CreateChatCompletionRequest;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// One recorded exchange, stored as a line of JSON
+#[derive(Serialize, Deserialize)]
+pub struct CassetteEntry {
+    pub request: CreateChatCompletionRequest,
+    /// The raw body of the `OpenRouterResponse`, including fields we don't read
+    pub response: serde_json::Value,
+}
+
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// Records LLM responses to a file, or answers requests from one without touching the network
+pub struct Cassette {
+    pub mode: CassetteMode,
+    path: PathBuf,
+    entries: Mutex<Vec<CassetteEntry>>,
+}
+
+impl Cassette {
+    /// Starts a fresh recording, overwriting anything at `path`
+    pub fn record(path: &Path) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::File::create(path)?;
+        Ok(Self {
+            mode: CassetteMode::Record,
+            path: path.to_path_buf(),
+            entries: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn replay(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line).map_err(std::io::Error::other)?);
+        }
+        Ok(Self {
+            mode: CassetteMode::Replay,
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, CassetteMode::Replay)
+    }
+
+    /// Takes the earliest recorded response to an identical request, so repeated requests replay in order
+    pub fn take_response(
+        &self,
+        request: &CreateChatCompletionRequest,
+    ) -> Option<serde_json::Value> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|entry| &entry.request == request)?;
+        Some(entries.remove(index).response)
+    }
+
+    /// Appends straight to the file so a crash keeps everything recorded so far
+    pub fn store(
+        &self,
+        request: &CreateChatCompletionRequest,
+        response: &serde_json::Value,
+    ) -> std::io::Result<()> {
+        let entry = CassetteEntry {
+            request: request.clone(),
+            response: response.clone(),
+        };
+        let line = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+        let _guard = self.entries.lock().unwrap();
+        let mut file = std::fs::OpenOptions::new().append(true).open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cassette;
+    use async_openai::types::chat::{
+        ChatCompletionRequestSystemMessage, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs,
+    };
+    use serde_json::json;
+
+    fn request(prompt: &str) -> CreateChatCompletionRequest {
+        CreateChatCompletionRequestArgs::default()
+            .model("test/model")
+            .messages(vec![
+                ChatCompletionRequestSystemMessage::from(prompt).into(),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let path = std::env::temp_dir().join(format!("cassette-{}.jsonl", std::process::id()));
+        let recording = Cassette::record(&path).unwrap();
+        recording
+            .store(&request("vote"), &json!({ "answer": 1 }))
+            .unwrap();
+        recording
+            .store(&request("talk"), &json!({ "answer": 2 }))
+            .unwrap();
+        recording
+            .store(&request("vote"), &json!({ "answer": 3 }))
+            .unwrap();
+
+        let replay = Cassette::replay(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(replay.is_replaying());
+        // Requests can be asked in another order, identical ones replay in the order they were recorded
+        assert_eq!(
+            replay.take_response(&request("talk")),
+            Some(json!({ "answer": 2 }))
+        );
+        assert_eq!(
+            replay.take_response(&request("vote")),
+            Some(json!({ "answer": 1 }))
+        );
+        assert_eq!(
+            replay.take_response(&request("vote")),
+            Some(json!({ "answer": 3 }))
+        );
+        assert_eq!(replay.take_response(&request("vote")), None);
+        assert_eq!(replay.take_response(&request("whisper")), None);
+    }
+}
@@ -1,11 +1,22 @@
 use serde::Deserialize;
 
 pub mod ai_interface;
+pub mod cassette;
 pub mod tools;
 
 #[derive(Deserialize, Debug)]
 struct OpenRouterResponse {
     choices: Vec<OpenRouterChoice>,
+    #[serde(default)]
+    usage: Option<OpenRouterUsage>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct OpenRouterUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -59,9 +59,75 @@ pub struct ToolInvocation {
     pub arguments: serde_json::Value,
 }
 
+/// The answer to a belief probe, see [`crate::game::beliefs`]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ReportSuspicions {
+    pub suspicions: Vec<Suspicion>,
+    pub reasoning: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Suspicion {
+    pub id: u8,
+    /// From 0 to 1
+    pub mafia_probability: f64,
+}
+
 define_tool!(Abstain, "Abstain from the current action");
 define_tool!(Whisper, "Whisper a message to another player");
 define_tool!(TagPlayerForComment, "Tag a player for comment");
 define_tool!(ProvideID, "Provide the ID of another player");
 define_tool!(Talk, "Talk to another player");
 define_tool!(MultiCall, "Invoke multiple tools in sequence");
+define_tool!(
+    ReportSuspicions,
+    "Privately report how likely each other living player is to be Mafia"
+);
+
+/// Offered on every turn of a discussion
+pub fn discussion_tools() -> Vec<async_openai::types::chat::ChatCompletionTools> {
+    vec![
+        Abstain::make_tool(),
+        Talk::make_tool(),
+        TagPlayerForComment::make_tool(),
+        Whisper::make_tool(),
+        MultiCall::make_tool(),
+    ]
+}
+
+/// Offered to every voter, by day and to the Mafia at night
+pub fn voting_tools() -> Vec<async_openai::types::chat::ChatCompletionTools> {
+    vec![
+        Abstain::make_tool(),
+        Talk::make_tool(),
+        ProvideID::make_tool(),
+        MultiCall::make_tool(),
+    ]
+}
+
+/// Offered for a night ability that targets one player
+pub fn pick_tools() -> Vec<async_openai::types::chat::ChatCompletionTools> {
+    vec![ProvideID::make_tool()]
+}
+
+pub fn make_tool_by_name(name: &str) -> Option<async_openai::types::chat::ChatCompletionTools> {
+    match name {
+        "Abstain" => Some(Abstain::make_tool()),
+        "Whisper" => Some(Whisper::make_tool()),
+        "TagPlayerForComment" => Some(TagPlayerForComment::make_tool()),
+        "ProvideID" => Some(ProvideID::make_tool()),
+        "Talk" => Some(Talk::make_tool()),
+        "MultiCall" => Some(MultiCall::make_tool()),
+        "ReportSuspicions" => Some(ReportSuspicions::make_tool()),
+        _ => None,
+    }
+}
+
+pub fn tool_name(tool: &async_openai::types::chat::ChatCompletionTools) -> Option<String> {
+    match tool {
+        async_openai::types::chat::ChatCompletionTools::Function(tool) => {
+            Some(tool.function.name.clone())
+        }
+        async_openai::types::chat::ChatCompletionTools::Custom(_) => None,
+    }
+}
@@ -0,0 +1,134 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Overrides the console's level, as a `tracing` filter such as `info` or `warn,rust::llm=debug`
+pub const CONSOLE_LEVEL_VAR: &str = "LOG_LEVEL";
+/// Overrides the log file's level in the same way
+pub const FILE_LEVEL_VAR: &str = "FILE_LOG_LEVEL";
+const FILE_LEVEL: &str = "debug";
+
+const LOG_FILE_NAME: &str = "mafia.log";
+/// The log file is rotated once it would grow past this
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// Rotated files kept next to the current one, as `mafia.log.1` and so on
+const KEPT_FILES: usize = 4;
+
+/// Sends everything logged to `console` and to a rotating log file in `directory`.
+/// The levels default to `console_level` and `debug` unless [`CONSOLE_LEVEL_VAR`] or [`FILE_LEVEL_VAR`] are set.
+/// Panics are logged as well, inside the spans they happened in.
+pub fn init<W>(directory: &Path, console: W, console_level: &str)
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let console_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(console)
+        .with_filter(filter(CONSOLE_LEVEL_VAR, console_level));
+    let file_layer = match RotatingFile::open(directory) {
+        Ok(file) => Some(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(filter(FILE_LEVEL_VAR, FILE_LEVEL)),
+        ),
+        Err(err) => {
+            eprintln!(
+                "Could not open a log file in {}: {}",
+                directory.display(),
+                err
+            );
+            None
+        }
+    };
+    if tracing_subscriber::registry()
+        .with(console_layer)
+        .with(file_layer)
+        .try_init()
+        .is_err()
+    {
+        return;
+    }
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{}", info);
+        default_hook(info);
+    }));
+}
+
+/// For the command line tools: warnings and errors on stdout, the rest in `directory`
+pub fn init_headless(directory: &Path) {
+    init(directory, std::io::stdout, "warn");
+}
+
+fn filter(variable: &str, default: &str) -> EnvFilter {
+    match std::env::var(variable) {
+        Ok(directives) => EnvFilter::try_new(&directives).unwrap_or_else(|err| {
+            eprintln!("Ignoring {}: {}", variable, err);
+            EnvFilter::new(default)
+        }),
+        Err(_) => EnvFilter::new(default),
+    }
+}
+
+struct RotatingFile {
+    directory: PathBuf,
+    file: std::fs::File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(directory: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(directory.join(LOG_FILE_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        match index {
+            0 => self.directory.join(LOG_FILE_NAME),
+            _ => self.directory.join(format!("{}.{}", LOG_FILE_NAME, index)),
+        }
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        // Renaming onto an existing file fails on Windows
+        let _ = std::fs::remove_file(self.path(KEPT_FILES));
+        for index in (0..KEPT_FILES).rev() {
+            let path = self.path(index);
+            if path.exists() {
+                std::fs::rename(path, self.path(index + 1))?;
+            }
+        }
+        self.file = std::fs::File::create(self.path(0))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
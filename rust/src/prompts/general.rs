@@ -1,28 +1,57 @@
 use crate::actor::BaseActor;
-use crate::data::roles::GameRole;
+use crate::data::roles::{GameRole, RoleAlignment, RoleDefinition, WinCondition};
 use crate::game::EndResult;
+use crate::prompts::PromptOverrides;
 
-pub fn utter_beginning(actor_count: u8, extra_messages: u8) -> String {
-    format!(
-        include_str!("utter_beginning.txt"),
-        actor_count, extra_messages
-    )
+/// Placeholders: `{actor_count}`, `{extra_messages}`
+pub fn utter_beginning(overrides: &PromptOverrides, actor_count: u8, extra_messages: u8) -> String {
+    overrides
+        .render(
+            "utter_beginning",
+            &[
+                ("actor_count", &actor_count),
+                ("extra_messages", &extra_messages),
+            ],
+        )
+        .unwrap_or_else(|| {
+            format!(
+                include_str!("utter_beginning.txt"),
+                actor_count, extra_messages
+            )
+        })
 }
 
-pub fn introduce_you(actor: &BaseActor) -> String {
-    format!(
-        "You are {} (ID {}) with the role of {}: {}",
-        actor.name,
-        actor.id,
-        actor.role.name(),
-        actor.role.description()
-    )
+/// Placeholders: `{name}`, `{id}`, `{role}`, `{description}`
+pub fn introduce_you(
+    overrides: &PromptOverrides,
+    actor: &BaseActor,
+    definition: &RoleDefinition,
+) -> String {
+    overrides
+        .render(
+            "introduce_you",
+            &[
+                ("name", &actor.name),
+                ("id", &actor.id),
+                ("role", &actor.role.name()),
+                ("description", &definition.description),
+            ],
+        )
+        .unwrap_or_else(|| {
+            format!(
+                "You are {} (ID {}) with the role of {}: {}",
+                actor.name,
+                actor.id,
+                actor.role.name(),
+                definition.description
+            )
+        })
 }
 
-pub fn build_role_list(roles: &[&GameRole]) -> String {
+pub fn build_role_list(roles: &[&RoleDefinition]) -> String {
     let mut builder = String::from("The roles in the game are:");
     for role in roles {
-        builder.push_str(&format!("\n{}: {}", role.name(), role.description()));
+        builder.push_str(&format!("\n{}: {}", role.name, role.description));
     }
     builder
 }
@@ -35,16 +64,25 @@ pub fn build_actor_list(actors: &[BaseActor]) -> String {
     builder
 }
 
-pub fn actor_was_killed(actor: &BaseActor) -> String {
+/// `killer` is the role of whoever killed them on their own, `None` for the mafia
+pub fn actor_was_killed(actor: &BaseActor, killer: Option<&GameRole>) -> String {
+    let by = match killer {
+        Some(role) => format!("a {}", role.name()),
+        None => String::from("the mafia"),
+    };
     format!(
-        "{} was killed! They were a {}.",
+        "{} was killed by {}! They were a {}.",
         actor.name,
+        by,
         actor.role.name()
     )
 }
 
-pub fn day_time(day_count: u8) -> String {
-    if day_count == 0 {
+/// Placeholders: `{day_count}`
+pub fn day_time(overrides: &PromptOverrides, day_count: u8) -> String {
+    if let Some(text) = overrides.render("day_time", &[("day_count", &day_count)]) {
+        text
+    } else if day_count == 0 {
         format!(
             "It is now day {}--the first day. After discussion ends, voting will begin--remember that you cannot vote during the discussion.",
             day_count
@@ -57,8 +95,11 @@ pub fn day_time(day_count: u8) -> String {
     }
 }
 
-pub fn night_time(night_count: u8) -> String {
-    if night_count == 0 {
+/// Placeholders: `{night_count}`
+pub fn night_time(overrides: &PromptOverrides, night_count: u8) -> String {
+    if let Some(text) = overrides.render("night_time", &[("night_count", &night_count)]) {
+        text
+    } else if night_count == 0 {
         format!(
             "It is now night {}--the first night. Night acting groups will make their move now.",
             night_count
@@ -71,14 +112,32 @@ pub fn night_time(night_count: u8) -> String {
     }
 }
 
-pub fn your_turn_to_talk(actor: &BaseActor, core_messages: u8, extra_messages: u8) -> String {
-    format!(
-        "It's now your turn to talk. {} core messages and {} extra messages remain. Remember: you are {}, a {}.",
-        core_messages,
-        extra_messages,
-        actor.name,
-        actor.role.name()
-    )
+/// Placeholders: `{core_messages}`, `{extra_messages}`, `{name}`, `{role}`
+pub fn your_turn_to_talk(
+    overrides: &PromptOverrides,
+    actor: &BaseActor,
+    core_messages: u8,
+    extra_messages: u8,
+) -> String {
+    overrides
+        .render(
+            "your_turn_to_talk",
+            &[
+                ("core_messages", &core_messages),
+                ("extra_messages", &extra_messages),
+                ("name", &actor.name),
+                ("role", &actor.role.name()),
+            ],
+        )
+        .unwrap_or_else(|| {
+            format!(
+                "It's now your turn to talk. {} core messages and {} extra messages remain. Remember: you are {}, a {}.",
+                core_messages,
+                extra_messages,
+                actor.name,
+                actor.role.name()
+            )
+        })
 }
 
 pub fn tagged_for_comment(tagger: &BaseActor, tagged: &BaseActor) -> String {
@@ -89,8 +148,10 @@ pub fn voting_begins() -> &'static str {
     "Voting has begun."
 }
 
-pub fn time_to_vote() -> &'static str {
-    "It's now your turn to vote. You can abstain or use the Talk tool to leave a comment or explanation if you wish."
+pub fn time_to_vote(overrides: &PromptOverrides) -> String {
+    overrides.render("time_to_vote", &[]).unwrap_or_else(|| {
+        "It's now your turn to vote. You can abstain or use the Talk tool to leave a comment or explanation if you wish.".to_string()
+    })
 }
 
 pub fn actor_voted(
@@ -141,13 +202,66 @@ pub fn whispered(from: &BaseActor, message: &str) -> String {
     )
 }
 
-pub fn game_end(end_result: &EndResult) -> String {
+/// Placeholders: `{players}`
+pub fn belief_probe(overrides: &PromptOverrides, others: &[&BaseActor]) -> String {
+    let players = others
+        .iter()
+        .map(|actor| format!("{} (ID {})", actor.name, actor.id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    overrides
+        .render("belief_probe", &[("players", &players)])
+        .unwrap_or_else(|| {
+            format!(
+                "This question is private: nobody else will see it or your answer, and it does not affect the game. For each of these players, give the probability from 0 to 1 that they are Mafia, then explain your reasoning: {}",
+                players
+            )
+        })
+}
+
+pub fn personal_win(actor: &BaseActor, condition: WinCondition) -> String {
+    format!(
+        "{} has won on their own: {}.",
+        actor.name,
+        win_reason(condition)
+    )
+}
+
+fn win_reason(condition: WinCondition) -> &'static str {
+    match condition {
+        WinCondition::VotedOut => "they were voted out, just as they wanted",
+        WinCondition::LastStanding => "they were the last one standing",
+    }
+}
+
+pub fn game_end(end_result: &EndResult, actors: &[BaseActor]) -> String {
     let mut builder = String::from("The game has ended.\n");
-    match end_result {
-        EndResult::Mafia => {
+    match end_result.faction {
+        Some(RoleAlignment::Mafia) => {
             builder.push_str("The mafia won--they reached an equal amount of players with town")
         }
-        EndResult::Town => builder.push_str("The town won--all mafia were eliminated"),
+        Some(RoleAlignment::Town) => builder.push_str("The town won--all mafia were eliminated"),
+        Some(RoleAlignment::Neutral) | None => builder.push_str("No faction won"),
+    }
+    let won = if end_result.faction.is_some() {
+        "also won"
+    } else {
+        "won"
+    };
+    for win in &end_result.personal_wins {
+        let name = actors
+            .iter()
+            .find(|actor| actor.id == win.actor_id)
+            .map_or_else(
+                || format!("ID {}", win.actor_id),
+                |actor| actor.name.clone(),
+            );
+        builder.push_str(&format!(
+            "\n{} {}--{}",
+            name,
+            won,
+            win_reason(win.condition)
+        ));
     }
     builder
 }
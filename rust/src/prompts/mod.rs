@@ -1,2 +1,55 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
 pub mod general;
 pub mod specific;
+
+/// Replacement templates for prompts, keyed by the name of the function that builds them.
+/// Each function documents the `{placeholders}` its template can use.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PromptOverrides {
+    pub templates: BTreeMap<String, String>,
+}
+
+impl PromptOverrides {
+    /// Every prompt that can be overridden
+    pub const NAMES: [&'static str; 12] = [
+        "utter_beginning",
+        "introduce_you",
+        "day_time",
+        "night_time",
+        "your_turn_to_talk",
+        "time_to_vote",
+        "pick_to_protect",
+        "pick_to_investigate",
+        "pick_to_kill",
+        "pick_to_block",
+        "mafia_discussion_begin",
+        "belief_probe",
+    ];
+
+    /// Fills the template for `name` with `values`, if there is one
+    pub fn render(&self, name: &str, values: &[(&str, &dyn Display)]) -> Option<String> {
+        let mut text = self.templates.get(name)?.clone();
+        for (key, value) in values {
+            text = text.replace(&format!("{{{}}}", key), &value.to_string());
+        }
+        Some(text)
+    }
+
+    /// Applies `other` over these, its templates replacing those of the same name
+    pub fn merge(&mut self, other: &PromptOverrides) {
+        self.templates.extend(other.templates.clone());
+    }
+
+    /// Template names that no prompt uses, most likely typos
+    pub fn unknown_names(&self) -> Vec<&str> {
+        self.templates
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !Self::NAMES.contains(name))
+            .collect()
+    }
+}
@@ -1,8 +1,12 @@
 pub mod doctor {
     use crate::actor::BaseActor;
+    use crate::prompts::PromptOverrides;
 
-    pub fn pick_to_protect() -> &'static str {
-        "Doctor, it's now your turn to pick a player to protect."
+    /// Placeholders: `{role}`
+    pub fn pick_to_protect(overrides: &PromptOverrides, role: &str) -> String {
+        overrides
+            .render("pick_to_protect", &[("role", &role)])
+            .unwrap_or_else(|| format!("{}, it's now your turn to pick a player to protect.", role))
     }
 
     pub fn you_chose_to_protect(target: &BaseActor) -> String {
@@ -20,13 +24,23 @@ pub mod doctor {
 pub mod sheriff {
     use crate::actor::BaseActor;
     use crate::data::roles::RoleAlignment;
+    use crate::prompts::PromptOverrides;
 
-    pub fn pick_to_investigate() -> &'static str {
-        "Sheriff, it's now your turn to pick a player to investigate."
+    /// Placeholders: `{role}`
+    pub fn pick_to_investigate(overrides: &PromptOverrides, role: &str) -> String {
+        overrides
+            .render("pick_to_investigate", &[("role", &role)])
+            .unwrap_or_else(|| {
+                format!(
+                    "{}, it's now your turn to pick a player to investigate.",
+                    role
+                )
+            })
     }
 
-    pub fn investigate_result(actor: &BaseActor) -> String {
-        match actor.role.alignment() {
+    /// `alignment` is what the investigation found, which isn't always the actor's
+    pub fn investigate_result(actor: &BaseActor, alignment: &RoleAlignment) -> String {
+        match alignment {
             RoleAlignment::Mafia => {
                 format!("You chose to investigate {}, they are mafia!", actor.name)
             }
@@ -40,6 +54,7 @@ pub mod sheriff {
 
 pub mod mafia {
     use crate::actor::BaseActor;
+    use crate::prompts::PromptOverrides;
 
     pub fn build_mafia_list(mafias: &[&BaseActor]) -> String {
         let mut builder = String::from("As a mafia, you know your fellow mafia:");
@@ -49,7 +64,57 @@ pub mod mafia {
         builder
     }
 
-    pub fn mafia_discussion_begin() -> &'static str {
-        "Mafia, it's now your turn to discuss. You are now in a private channel--nobody else but your fellow mafia can hear you. You can not only use this opportunity to discuss potential targets, but also plan ahead with your fellow mafia. Remember that you cannot vote during the discussion."
+    pub fn mafia_discussion_begin(overrides: &PromptOverrides) -> String {
+        overrides.render("mafia_discussion_begin", &[]).unwrap_or_else(|| {
+            "Mafia, it's now your turn to discuss. You are now in a private channel--nobody else but your fellow mafia can hear you. You can not only use this opportunity to discuss potential targets, but also plan ahead with your fellow mafia. Remember that you cannot vote during the discussion.".to_string()
+        })
+    }
+}
+
+/// For the night actions no built-in role has
+pub mod night {
+    use crate::actor::BaseActor;
+    use crate::prompts::PromptOverrides;
+
+    /// Placeholders: `{role}`
+    pub fn pick_to_kill(overrides: &PromptOverrides, role: &str) -> String {
+        overrides
+            .render("pick_to_kill", &[("role", &role)])
+            .unwrap_or_else(|| format!("{}, it's now your turn to pick a player to kill.", role))
+    }
+
+    /// Placeholders: `{role}`
+    pub fn pick_to_block(overrides: &PromptOverrides, role: &str) -> String {
+        overrides
+            .render("pick_to_block", &[("role", &role)])
+            .unwrap_or_else(|| {
+                format!(
+                    "{}, it's now your turn to pick a player to block. They won't be able to act tonight.",
+                    role
+                )
+            })
+    }
+
+    pub fn uses_left(uses: usize) -> String {
+        match uses {
+            1 => String::from("You can only do this once more."),
+            _ => format!("You can do this {} more times.", uses),
+        }
+    }
+
+    pub fn invalid_target(target: &BaseActor) -> String {
+        format!("{} can't be picked, so nothing happened.", target.name)
+    }
+
+    pub fn you_chose_to_kill(target: &BaseActor) -> String {
+        format!("You chose to kill {}.", target.name)
+    }
+
+    pub fn you_chose_to_block(target: &BaseActor) -> String {
+        format!("You chose to block {}.", target.name)
+    }
+
+    pub fn you_were_blocked() -> &'static str {
+        "Someone blocked you, you can't act tonight."
     }
 }
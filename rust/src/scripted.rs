@@ -0,0 +1,148 @@
+use crate::actor::{BaseActor, PromptReply};
+use crate::data::action::Action;
+use crate::data::channel::Channel;
+use crate::data::event::GameEvent;
+use crate::data::roles::{NightActionKind, RoleAlignment};
+use crate::game::{Game, GameRng};
+use crate::llm::tools::tool_name;
+use async_openai::types::chat::ChatCompletionTools;
+use rand::SeedableRng;
+use rand::seq::IndexedRandom;
+
+const ACCUSATION_PREFIX: &str = "I am the Sheriff, and I found out that ID ";
+
+/// A cheap stand-in for a model that plays a fixed, simple strategy, for balancing setups.
+/// Sheriffs reveal the Mafia they find, Town votes for whoever was revealed last,
+/// and the Mafia go after the revealing Sheriff first. Everything else is random.
+pub fn decide(actor: &BaseActor, game: &Game, tools: &[ChatCompletionTools]) -> PromptReply {
+    let tools = tools.iter().filter_map(tool_name).collect::<Vec<_>>();
+    // Derived from the game's seed rather than drawn from its RNG, so scripted actors don't
+    // shift the random choices of the rest of the game
+    let mut rng = GameRng::seed_from_u64(
+        game.seed ^ ((game.state.context.len() as u64) << 8) ^ actor.id as u64,
+    );
+    let action = if tools.iter().any(|tool| tool == "ProvideID") {
+        let night_pick = !tools.iter().any(|tool| tool == "Talk");
+        pick_target(actor, game, night_pick, &mut rng).map_or(Action::Abstain, Action::ProvideID)
+    } else {
+        match found_mafia(actor, game).first() {
+            Some(id) => Action::Talk(format!("{}{} is Mafia.", ACCUSATION_PREFIX, id)),
+            None => Action::Talk(String::from("I have nothing to share yet.")),
+        }
+    };
+    PromptReply {
+        action,
+        raw: None,
+        tool_calls: Vec::new(),
+    }
+}
+
+fn pick_target(actor: &BaseActor, game: &Game, night_pick: bool, rng: &mut GameRng) -> Option<u8> {
+    let alive = game.state.get_nondead_ids();
+    let is_mafia = |id: &u8| {
+        game.state
+            .get_actor_from_id(*id)
+            .is_some_and(|other| game.state.role_of(other).alignment == RoleAlignment::Mafia)
+    };
+    let others = alive
+        .iter()
+        .copied()
+        .filter(|id| *id != actor.id)
+        .collect::<Vec<_>>();
+    let definition = game.state.role_of(actor);
+    let night_action = definition
+        .night_action
+        .as_ref()
+        .map(|action| action.kind)
+        .filter(|_| night_pick);
+    match night_action {
+        _ if definition.alignment == RoleAlignment::Mafia => {
+            let town = others
+                .into_iter()
+                .filter(|id| !is_mafia(id))
+                .collect::<Vec<_>>();
+            last_accuser(game)
+                .filter(|id| town.contains(id))
+                .or_else(|| town.choose(rng).copied())
+        }
+        Some(NightActionKind::Investigate) => {
+            let investigated = investigated_by(actor, game);
+            let fresh = others
+                .iter()
+                .copied()
+                .filter(|id| !investigated.contains(id))
+                .collect::<Vec<_>>();
+            fresh.choose(rng).or_else(|| others.choose(rng)).copied()
+        }
+        Some(NightActionKind::Protect) => last_accuser(game)
+            .filter(|id| alive.contains(id))
+            .or_else(|| alive.choose(rng).copied()),
+        _ => found_mafia(actor, game)
+            .first()
+            .copied()
+            .or_else(|| last_accused(game).filter(|id| others.contains(id)))
+            .or_else(|| others.choose(rng).copied()),
+    }
+}
+
+fn investigated_by(actor: &BaseActor, game: &Game) -> Vec<u8> {
+    game.state
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            GameEvent::Investigated {
+                sheriff, target, ..
+            } if *sheriff == actor.id => Some(*target),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Living Mafia this actor investigated itself
+fn found_mafia(actor: &BaseActor, game: &Game) -> Vec<u8> {
+    let alive = game.state.get_nondead_ids();
+    game.state
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            GameEvent::Investigated {
+                sheriff,
+                target,
+                alignment: RoleAlignment::Mafia,
+            } if *sheriff == actor.id && alive.contains(target) => Some(*target),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The most recent public accusation as `(accuser, accused)`
+fn last_accusation(game: &Game) -> Option<(u8, u8)> {
+    game.state
+        .events
+        .iter()
+        .rev()
+        .find_map(|event| match event {
+            GameEvent::Spoke {
+                actor_id,
+                channel: Channel::Global,
+                message,
+            } => {
+                let accused = message
+                    .strip_prefix(ACCUSATION_PREFIX)?
+                    .split(' ')
+                    .next()?
+                    .parse()
+                    .ok()?;
+                Some((*actor_id, accused))
+            }
+            _ => None,
+        })
+}
+
+fn last_accuser(game: &Game) -> Option<u8> {
+    last_accusation(game).map(|(accuser, _)| accuser)
+}
+
+fn last_accused(game: &Game) -> Option<u8> {
+    last_accusation(game).map(|(_, accused)| accused)
+}
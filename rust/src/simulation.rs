@@ -0,0 +1,549 @@
+use crate::actor::{ActorKind, BaseActor, ModelCustomization};
+use crate::data::roles::{GameRole, RoleAlignment, RoleBook, RoleDefinition};
+use crate::export::analytics::GameAnalytics;
+use crate::export::transcript::Transcript;
+use crate::frontend::GameFrontend;
+use crate::frontend::headless::NullFrontend;
+use crate::game::beliefs::ProbePoint;
+use crate::game::events::EventLog;
+use crate::game::save::SaveFile;
+use crate::game::setup::GameSetup;
+use crate::game::{EndResult, Game};
+use crate::llm::ai_interface::AIInterface;
+use crate::prompts::PromptOverrides;
+use async_openai::types::chat::ReasoningEffort;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::Instrument;
+
+/// A lobby and rules to play without Godot, read from JSON
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    #[serde(default = "default_api_base")]
+    pub api_base: String,
+    /// Falls back to the `API_KEY` environment variable
+    #[serde(default)]
+    pub api_key: Option<String>,
+    pub actors: Vec<ActorConfig>,
+    /// Game `n` of a batch is played with `seed + n`
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub start_at_night: bool,
+    #[serde(default)]
+    pub prompts: PromptOverrides,
+    /// The classic setup when unset
+    #[serde(default)]
+    pub setup: Option<GameSetup>,
+    /// Where every living LLM actor is privately asked who they suspect
+    #[serde(default)]
+    pub belief_probes: Vec<ProbePoint>,
+    /// Added to the built-in roles, replacing those of the same name
+    #[serde(default)]
+    pub roles: Vec<RoleDefinition>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ActorConfig {
+    pub name: String,
+    #[serde(default)]
+    pub model_id: String,
+    /// Plays the fixed strategy of [`crate::scripted`] instead of asking `model_id`
+    #[serde(default)]
+    pub scripted: bool,
+    /// Dealt randomly when unset
+    #[serde(default)]
+    pub role: Option<GameRole>,
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+pub(crate) fn default_api_base() -> String {
+    String::from("https://openrouter.ai/api/v1")
+}
+
+impl SimulationConfig {
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(std::io::Error::other)
+    }
+
+    /// The roles the lobby's games are dealt from
+    pub fn role_book(&self) -> RoleBook {
+        let mut roles = RoleBook::default();
+        roles.extend(self.roles.clone());
+        roles
+    }
+
+    /// Checks the setup against the number of actors and that every given role is defined
+    pub fn validate(&self) -> Result<(), String> {
+        let roles = self.role_book();
+        if let Some(actor) = self.actors.iter().find(|actor| {
+            actor
+                .role
+                .as_ref()
+                .is_some_and(|role| !roles.contains(role))
+        }) {
+            return Err(format!(
+                "{} plays {}, which isn't defined",
+                actor.name,
+                actor.role.as_ref().unwrap().name()
+            ));
+        }
+        self.setup
+            .clone()
+            .unwrap_or_default()
+            .validate(self.actors.len(), &roles)
+    }
+
+    pub fn api_key(&self) -> String {
+        self.api_key
+            .clone()
+            .or_else(|| std::env::var("API_KEY").ok())
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    }
+
+    pub fn build_actors(&self) -> Vec<BaseActor> {
+        self.actors
+            .iter()
+            .enumerate()
+            .map(|(index, actor)| BaseActor {
+                name: actor.name.clone(),
+                id: index as u8,
+                role: actor.role.clone().unwrap_or(GameRole::VILLAGER),
+                extra_data: Vec::new(),
+                kind: if actor.scripted {
+                    ActorKind::Scripted
+                } else {
+                    ActorKind::Llm(AIInterface {
+                        model_id: actor.model_id.clone(),
+                        owner_id: index as u8,
+                        instructions: actor.instructions.clone(),
+                        reasoning_effort: actor.reasoning_effort.clone(),
+                    })
+                },
+                model_customization: ModelCustomization {
+                    sprite_path: String::new(),
+                    color: [1.0, 1.0, 1.0, 1.0],
+                },
+            })
+            .collect()
+    }
+
+    /// Deals a new game and plays it until it ends or `max_phases` days and nights have passed.
+    /// Its events are streamed to `<events>/<game id>.jsonl` when `events` is set.
+    pub async fn play(
+        &self,
+        frontend: Box<dyn GameFrontend>,
+        seed: u64,
+        max_phases: Option<u32>,
+        events: Option<&Path>,
+    ) -> Result<Game, PlayError> {
+        self.validate().map_err(PlayError::Invalid)?;
+        let mut game = Game::new(frontend, None, seed);
+        if let Some(directory) = events {
+            let path = directory.join(format!("{}.jsonl", game.id));
+            match EventLog::create(&path) {
+                Ok(log) => game.subscribe(Box::new(log)),
+                Err(err) => tracing::error!("Failed to create {}: {}", path.display(), err),
+            }
+        }
+        game.prompts = self.prompts.clone();
+        game.state.roles = self.role_book();
+        game.belief_probes = self.belief_probes.clone();
+        if let Some(setup) = &self.setup {
+            game.setup = setup.clone();
+        }
+        let span = game.span();
+        async {
+            game.init_actors(self.build_actors());
+            game.init_context(self.start_at_night);
+            let mut phases = 0;
+            while game.end_result.is_none()
+                && game.aborted.is_none()
+                && max_phases.is_none_or(|max| phases < max)
+            {
+                game.iterate().await;
+                phases += 1;
+            }
+            game.announce_end();
+        }
+        .instrument(span)
+        .await;
+        match &game.aborted {
+            Some(failure) => Err(PlayError::Aborted {
+                failure: failure.to_string(),
+                game: Box::new(game),
+            }),
+            None => Ok(game),
+        }
+    }
+}
+
+/// Why [`SimulationConfig::play`] has no finished game
+pub enum PlayError {
+    /// The lobby doesn't pass [`SimulationConfig::validate`], nothing was played
+    Invalid(String),
+    /// A turn failed and the game was aborted, it is kept as far as it got
+    Aborted { failure: String, game: Box<Game> },
+}
+
+impl Display for PlayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayError::Invalid(err) => write!(f, "{}", err),
+            PlayError::Aborted { failure, .. } => write!(f, "{}", failure),
+        }
+    }
+}
+
+pub struct BatchOptions {
+    /// Games to run at the same time
+    pub parallel: usize,
+    pub max_phases: Option<u32>,
+    /// Stop starting new games once one has failed
+    pub fail_fast: bool,
+    /// Saves and transcripts of every game are written here when set
+    pub out: Option<PathBuf>,
+    /// Keep reasoning entries in the transcripts
+    pub reasoning: bool,
+    /// Stream every game's events to `<out>/events` as they happen
+    pub events: bool,
+    /// Don't print a line for every finished game
+    pub quiet: bool,
+}
+
+pub struct BatchGame {
+    pub summary: GameSummary,
+    /// `None` when the game failed
+    pub save: Option<SaveFile>,
+}
+
+/// Plays every lobby with its seed, each in its own task so a panic only takes down that game.
+/// The results are in the same order as `lobbies`.
+pub async fn run_batch(
+    lobbies: Vec<(SimulationConfig, u64)>,
+    options: BatchOptions,
+) -> Vec<BatchGame> {
+    let total = lobbies.len();
+    let lobbies = Arc::new(lobbies);
+    let options = Arc::new(options);
+    let next_game = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let results = Arc::new(Mutex::new(Vec::new()));
+    let mut workers = Vec::new();
+    for _ in 0..options.parallel.max(1) {
+        let lobbies = Arc::clone(&lobbies);
+        let options = Arc::clone(&options);
+        let next_game = Arc::clone(&next_game);
+        let stop = Arc::clone(&stop);
+        let results = Arc::clone(&results);
+        workers.push(tokio::spawn(async move {
+            loop {
+                let index = next_game.fetch_add(1, Ordering::SeqCst);
+                if index >= lobbies.len() || stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let seed = lobbies[index].1;
+                let game_lobbies = Arc::clone(&lobbies);
+                let max_phases = options.max_phases;
+                let events = options
+                    .out
+                    .as_ref()
+                    .filter(|_| options.events)
+                    .map(|out| out.join("events"));
+                let game = tokio::spawn(async move {
+                    game_lobbies[index]
+                        .0
+                        .play(Box::new(NullFrontend), seed, max_phases, events.as_deref())
+                        .await
+                })
+                .await
+                .map_err(|err| {
+                    if err.is_panic() {
+                        panic_message(err.into_panic())
+                    } else {
+                        err.to_string()
+                    }
+                });
+                let result = match game {
+                    Ok(Ok(game)) => {
+                        let save = game.to_save();
+                        if let Some(out) = &options.out {
+                            write_game(&save, out, options.reasoning);
+                        }
+                        BatchGame {
+                            summary: GameSummary::from_save(index, &save),
+                            save: Some(save),
+                        }
+                    }
+                    // Written like a finished game, so the turn it failed on can be inspected
+                    Ok(Err(PlayError::Aborted { failure, game })) => {
+                        let save = game.to_save();
+                        if let Some(out) = &options.out {
+                            write_game(&save, out, options.reasoning);
+                        }
+                        BatchGame {
+                            summary: GameSummary {
+                                error: Some(failure),
+                                ..GameSummary::from_save(index, &save)
+                            },
+                            save: None,
+                        }
+                    }
+                    Ok(Err(err)) => BatchGame {
+                        summary: GameSummary::failed(index, seed, err.to_string()),
+                        save: None,
+                    },
+                    Err(message) => BatchGame {
+                        summary: GameSummary::failed(index, seed, message),
+                        save: None,
+                    },
+                };
+                if let Some(message) = &result.summary.error {
+                    if options.fail_fast {
+                        stop.store(true, Ordering::SeqCst);
+                    }
+                    tracing::error!(game = index + 1, seed, "Game failed: {}", message);
+                }
+                if !options.quiet {
+                    println!(
+                        "Game {}/{} (seed {}): {}",
+                        index + 1,
+                        total,
+                        seed,
+                        result.summary.outcome()
+                    );
+                }
+                results.lock().unwrap().push(result);
+            }
+        }));
+    }
+    for worker in workers {
+        worker.await.unwrap();
+    }
+    let mut results = std::mem::take(&mut *results.lock().unwrap());
+    results.sort_by_key(|result| result.summary.index);
+    results
+}
+
+fn write_game(save: &SaveFile, out: &Path, reasoning: bool) {
+    let path = out.join("saves").join(format!("{}.json", save.game_id));
+    if let Err(err) = save.write(&path) {
+        tracing::error!("Failed to write {}: {}", path.display(), err);
+    }
+    if let Err(err) = Transcript::from_save(save, reasoning).write_all(&out.join("transcripts")) {
+        tracing::error!(
+            "Failed to write the transcript of game {}: {}",
+            save.game_id,
+            err
+        );
+    }
+    if let Err(err) = GameAnalytics::from_save(save).write(&out.join("analytics")) {
+        tracing::error!(
+            "Failed to write the analytics of game {}: {}",
+            save.game_id,
+            err
+        );
+    }
+}
+
+pub(crate) fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("Unknown panic")
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ActorSummary {
+    pub id: u8,
+    pub name: String,
+    pub model_id: Option<String>,
+    pub role: GameRole,
+    pub alignment: RoleAlignment,
+    pub survived: bool,
+    pub won: bool,
+}
+
+/// The outcome of one game of a batch
+#[derive(Clone, Serialize)]
+pub struct GameSummary {
+    pub index: usize,
+    pub seed: u64,
+    pub game_id: Option<String>,
+    /// `None` when the game was stopped early or failed
+    pub result: Option<EndResult>,
+    pub days: u8,
+    pub nights: u8,
+    pub actors: Vec<ActorSummary>,
+    /// What the game panicked or was aborted with, if it failed
+    pub error: Option<String>,
+}
+
+impl GameSummary {
+    pub fn from_save(index: usize, save: &SaveFile) -> Self {
+        let result = save.final_result();
+        let alive = save.state.get_nondead_ids();
+        Self {
+            index,
+            seed: save.seed,
+            game_id: Some(save.game_id.clone()),
+            days: save.day_night_count.day_count,
+            nights: save.day_night_count.night_count,
+            actors: save
+                .state
+                .actors
+                .iter()
+                .map(|actor| ActorSummary {
+                    id: actor.id,
+                    name: actor.name.clone(),
+                    model_id: match &actor.kind {
+                        ActorKind::Llm(llm) => Some(llm.model_id.clone()),
+                        ActorKind::Real | ActorKind::Scripted => None,
+                    },
+                    role: actor.role.clone(),
+                    alignment: save.state.role_of(actor).alignment.clone(),
+                    survived: alive.contains(&actor.id),
+                    won: result
+                        .as_ref()
+                        .is_some_and(|result| result.is_win_for(actor, &save.state.roles)),
+                })
+                .collect(),
+            result,
+            error: None,
+        }
+    }
+
+    pub fn outcome(&self) -> String {
+        match (&self.error, &self.result) {
+            (Some(error), _) => format!("failed: {}", error),
+            (None, Some(result)) => result.label(),
+            (None, None) => String::from("stopped early"),
+        }
+    }
+
+    pub fn failed(index: usize, seed: u64, error: String) -> Self {
+        Self {
+            index,
+            seed,
+            game_id: None,
+            result: None,
+            days: 0,
+            nights: 0,
+            actors: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Default, Serialize)]
+pub struct ModelRecord {
+    pub model_id: String,
+    pub games: u32,
+    pub wins: u32,
+    pub survived: u32,
+}
+
+/// Totals over a batch
+#[derive(Default, Serialize)]
+pub struct BatchSummary {
+    pub games: Vec<GameSummary>,
+    pub mafia_wins: u32,
+    pub town_wins: u32,
+    /// Counted apart from the faction wins, a game can have several
+    pub personal_wins: u32,
+    pub unfinished: u32,
+    pub failed: u32,
+    pub models: Vec<ModelRecord>,
+}
+
+impl BatchSummary {
+    pub fn new(mut games: Vec<GameSummary>) -> Self {
+        games.sort_by_key(|game| game.index);
+        let mut summary = BatchSummary::default();
+        for game in &games {
+            match (&game.result, &game.error) {
+                // Aborted games keep their actors, but only count as failed
+                (_, Some(_)) => {
+                    summary.failed += 1;
+                    continue;
+                }
+                (Some(result), None) => {
+                    match result.faction {
+                        Some(RoleAlignment::Mafia) => summary.mafia_wins += 1,
+                        Some(RoleAlignment::Town) => summary.town_wins += 1,
+                        Some(RoleAlignment::Neutral) | None => {}
+                    }
+                    summary.personal_wins += result.personal_wins.len() as u32;
+                }
+                (None, None) => summary.unfinished += 1,
+            }
+            for actor in &game.actors {
+                let Some(model_id) = &actor.model_id else {
+                    continue;
+                };
+                let record = match summary
+                    .models
+                    .iter()
+                    .position(|record| record.model_id == *model_id)
+                {
+                    Some(index) => &mut summary.models[index],
+                    None => {
+                        summary.models.push(ModelRecord {
+                            model_id: model_id.clone(),
+                            ..Default::default()
+                        });
+                        summary.models.last_mut().unwrap()
+                    }
+                };
+                record.games += 1;
+                record.wins += actor.won as u32;
+                record.survived += actor.survived as u32;
+            }
+        }
+        summary
+            .models
+            .sort_by(|a, b| (b.wins * a.games.max(1)).cmp(&(a.wins * b.games.max(1))));
+        summary.games = games;
+        summary
+    }
+
+    /// A plain-text table of the results, for the terminal
+    pub fn render(&self) -> String {
+        let mut builder = format!(
+            "Games: {} (Mafia won {}, Town won {}, personal wins {}, unfinished {}, failed {})\n",
+            self.games.len(),
+            self.mafia_wins,
+            self.town_wins,
+            self.personal_wins,
+            self.unfinished,
+            self.failed
+        );
+        builder.push_str(&format!(
+            "{:<40} {:>6} {:>6} {:>8} {:>9}\n",
+            "Model", "Games", "Wins", "Win %", "Survived"
+        ));
+        for record in &self.models {
+            builder.push_str(&format!(
+                "{:<40} {:>6} {:>6} {:>7.1}% {:>9}\n",
+                record.model_id,
+                record.games,
+                record.wins,
+                100.0 * record.wins as f64 / record.games.max(1) as f64,
+                record.survived
+            ));
+        }
+        builder
+    }
+}
@@ -0,0 +1,467 @@
+use crate::data::roles::{GameRole, RoleAlignment, RoleBook};
+use crate::game::GameRng;
+use crate::game::setup::{GameSetup, RoleSlot};
+use crate::simulation::{
+    ActorConfig, ActorSummary, GameSummary, SimulationConfig, default_api_base,
+};
+use async_openai::types::chat::ReasoningEffort;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+const SEAT_NAMES: [&str; 16] = [
+    "Alex", "Blair", "Casey", "Drew", "Emery", "Finley", "Gray", "Harper", "Indy", "Jules", "Kai",
+    "Logan", "Morgan", "Noel", "Parker", "Quinn",
+];
+
+const STARTING_ELO: f64 = 1500.0;
+const K_FACTOR: f64 = 24.0;
+/// How much of a game's score comes from the team winning, the rest is from surviving
+const TEAM_WEIGHT: f64 = 0.75;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TournamentModel {
+    pub model_id: String,
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+/// A pool of models that take turns in every seat of a fixed-size table
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TournamentConfig {
+    #[serde(default = "default_api_base")]
+    pub api_base: String,
+    /// Falls back to the `API_KEY` environment variable
+    #[serde(default)]
+    pub api_key: Option<String>,
+    pub models: Vec<TournamentModel>,
+    pub players: usize,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub start_at_night: bool,
+    /// Dealt once to the seats, the classic setup when unset
+    #[serde(default)]
+    pub setup: Option<GameSetup>,
+}
+
+impl TournamentConfig {
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&text).map_err(std::io::Error::other)?;
+        if config.models.is_empty() || config.players == 0 || config.players > SEAT_NAMES.len() {
+            return Err(std::io::Error::other(format!(
+                "A tournament needs at least one model and between 1 and {} players",
+                SEAT_NAMES.len()
+            )));
+        }
+        // Otherwise a model would sit in several seats of the same game
+        if config.models.len() < config.players {
+            return Err(std::io::Error::other(format!(
+                "A tournament of {} players needs at least {} models",
+                config.players, config.players
+            )));
+        }
+        config
+            .setup()
+            .validate(config.players, &RoleBook::default())
+            .map_err(std::io::Error::other)?;
+        Ok(config)
+    }
+
+    pub fn setup(&self) -> GameSetup {
+        self.setup.clone().unwrap_or_default()
+    }
+
+    /// The role of every seat, dealt once from the setup with the tournament's seed
+    pub fn seat_roles(&self) -> Vec<GameRole> {
+        let mut roles = self.setup().deal(
+            self.players,
+            &RoleBook::default(),
+            &mut GameRng::seed_from_u64(self.seed),
+        );
+        roles.resize(self.players, GameRole::VILLAGER);
+        roles
+    }
+
+    /// A round is one game per model, rotating the models one seat further every game.
+    /// Over a round every model sits in every seat once, so it plays every role equally often.
+    pub fn schedule(&self, rounds: usize) -> Vec<(SimulationConfig, u64)> {
+        let roles = self.seat_roles();
+        // The seat roles as fixed slots, so the games don't draw the random ones again
+        let setup = GameSetup {
+            roles: roles.iter().cloned().map(RoleSlot::Fixed).collect(),
+            mafia_scaling: None,
+            ..self.setup()
+        };
+        let model_count = self.models.len();
+        (0..rounds * model_count)
+            .map(|game| {
+                let actors = roles
+                    .iter()
+                    .enumerate()
+                    .map(|(seat, role)| {
+                        let model = &self.models[(seat + game) % model_count];
+                        ActorConfig {
+                            name: SEAT_NAMES[seat].to_string(),
+                            model_id: model.model_id.clone(),
+                            role: Some(role.clone()),
+                            instructions: model.instructions.clone(),
+                            reasoning_effort: model.reasoning_effort.clone(),
+                            scripted: false,
+                        }
+                    })
+                    .collect();
+                let lobby = SimulationConfig {
+                    api_base: self.api_base.clone(),
+                    api_key: self.api_key.clone(),
+                    actors,
+                    seed: self.seed,
+                    start_at_night: self.start_at_night,
+                    prompts: Default::default(),
+                    setup: Some(setup.clone()),
+                    belief_probes: Vec::new(),
+                    roles: Vec::new(),
+                };
+                (lobby, self.seed.wrapping_add(game as u64))
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RoleRecord {
+    pub games: u32,
+    pub wins: u32,
+    pub survived: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ModelRating {
+    pub model_id: String,
+    pub elo: f64,
+    #[serde(flatten)]
+    pub record: RoleRecord,
+    /// Keyed by role name
+    pub roles: BTreeMap<String, RoleRecord>,
+}
+
+/// Ratings kept across tournaments in a local file
+#[derive(Default, Serialize, Deserialize)]
+pub struct Ratings {
+    pub models: Vec<ModelRating>,
+}
+
+impl Ratings {
+    /// A missing file is an empty table
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(std::io::Error::other),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+    }
+
+    fn rating_mut(&mut self, model_id: &str) -> &mut ModelRating {
+        let index = match self
+            .models
+            .iter()
+            .position(|rating| rating.model_id == model_id)
+        {
+            Some(index) => index,
+            None => {
+                self.models.push(ModelRating {
+                    model_id: model_id.to_string(),
+                    elo: STARTING_ELO,
+                    record: RoleRecord::default(),
+                    roles: BTreeMap::new(),
+                });
+                self.models.len() - 1
+            }
+        };
+        &mut self.models[index]
+    }
+
+    fn elo(&self, model_id: &str) -> f64 {
+        self.models
+            .iter()
+            .find(|rating| rating.model_id == model_id)
+            .map_or(STARTING_ELO, |rating| rating.elo)
+    }
+
+    /// Rates Mafia and Town seats against the average of the opposing team, on the team's result and surviving.
+    /// Neutrals win on their own, so they're rated alone against the rest of the table, on their personal win.
+    /// Games that were stopped early or failed don't count.
+    pub fn record(&mut self, game: &GameSummary) {
+        if game.result.is_none() {
+            return;
+        }
+        let average_elo = |include: &dyn Fn(&ActorSummary) -> bool| {
+            let elos = game
+                .actors
+                .iter()
+                .filter(|actor| include(actor))
+                .filter_map(|actor| actor.model_id.as_ref())
+                .map(|model_id| self.elo(model_id))
+                .collect::<Vec<_>>();
+            elos.iter().sum::<f64>() / elos.len().max(1) as f64
+        };
+        let mafia_elo = average_elo(&|actor| actor.alignment == RoleAlignment::Mafia);
+        let town_elo = average_elo(&|actor| actor.alignment == RoleAlignment::Town);
+        // Worked out before any rating changes, so the order of the seats doesn't matter
+        let updates = game
+            .actors
+            .iter()
+            .filter_map(|actor| {
+                let model_id = actor.model_id.as_ref()?;
+                let (own, opposing, score) = match actor.alignment {
+                    RoleAlignment::Mafia => (mafia_elo, town_elo, team_score(actor)),
+                    RoleAlignment::Town => (town_elo, mafia_elo, team_score(actor)),
+                    RoleAlignment::Neutral => (
+                        self.elo(model_id),
+                        average_elo(&|other| other.id != actor.id),
+                        actor.won as u8 as f64,
+                    ),
+                };
+                Some((actor, model_id, expected_score(own, opposing), score))
+            })
+            .collect::<Vec<_>>();
+        for (actor, model_id, expected, score) in updates {
+            let rating = self.rating_mut(model_id);
+            rating.elo += K_FACTOR * (score - expected);
+            for record in [
+                &mut rating.record,
+                rating.roles.entry(actor.role.name()).or_default(),
+            ] {
+                record.games += 1;
+                record.wins += actor.won as u32;
+                record.survived += actor.survived as u32;
+            }
+        }
+    }
+
+    /// A Markdown table sorted by Elo, with 95% intervals on the win rates and a column per role played
+    pub fn leaderboard(&self) -> String {
+        let mut models = self.models.iter().collect::<Vec<_>>();
+        models.sort_by(|a, b| b.elo.total_cmp(&a.elo));
+        // Every role any model has played
+        let role_names = self
+            .models
+            .iter()
+            .flat_map(|rating| rating.roles.keys())
+            .collect::<BTreeSet<_>>();
+        let mut builder = String::from("| # | Model | Elo | Games | Win % (95% CI) | Survival % |");
+        for name in &role_names {
+            builder.push_str(&format!(" {} Win % |", name));
+        }
+        builder.push_str("\n| --- | --- | --- | --- | --- | --- |");
+        builder.push_str(&" --- |".repeat(role_names.len()));
+        builder.push('\n');
+        for (rank, rating) in models.iter().enumerate() {
+            let (low, high) = wilson_interval(rating.record.wins, rating.record.games);
+            builder.push_str(&format!(
+                "| {} | {} | {:.0} | {} | {:.1} ({:.1}-{:.1}) | {:.1} |",
+                rank + 1,
+                rating.model_id,
+                rating.elo,
+                rating.record.games,
+                percentage(rating.record.wins, rating.record.games),
+                low * 100.0,
+                high * 100.0,
+                percentage(rating.record.survived, rating.record.games),
+            ));
+            for name in &role_names {
+                match rating.roles.get(*name) {
+                    Some(record) => builder.push_str(&format!(
+                        " {:.1} ({}) |",
+                        percentage(record.wins, record.games),
+                        record.games
+                    )),
+                    None => builder.push_str(" - |"),
+                }
+            }
+            builder.push('\n');
+        }
+        builder
+    }
+}
+
+/// The chance to win the Elo system gives a rating of `own` against one of `opposing`
+fn expected_score(own: f64, opposing: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opposing - own) / 400.0))
+}
+
+fn team_score(actor: &ActorSummary) -> f64 {
+    TEAM_WEIGHT * actor.won as u8 as f64 + (1.0 - TEAM_WEIGHT) * actor.survived as u8 as f64
+}
+
+fn percentage(count: u32, total: u32) -> f64 {
+    100.0 * count as f64 / total.max(1) as f64
+}
+
+/// The Wilson score interval, which stays sensible for small samples and 0% or 100% rates
+fn wilson_interval(wins: u32, games: u32) -> (f64, f64) {
+    if games == 0 {
+        return (0.0, 1.0);
+    }
+    let z = 1.96f64;
+    let n = games as f64;
+    let p = wins as f64 / n;
+    let denominator = 1.0 + z * z / n;
+    let center = (p + z * z / (2.0 * n)) / denominator;
+    let margin = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        K_FACTOR, Ratings, STARTING_ELO, TournamentConfig, TournamentModel, wilson_interval,
+    };
+    use crate::data::roles::{GameRole, RoleAlignment};
+    use crate::frontend::headless::NullFrontend;
+    use crate::game::setup::builtin_presets;
+    use crate::game::{EndResult, Game};
+    use crate::simulation::{ActorSummary, GameSummary};
+    use std::borrow::Cow;
+
+    const JESTER: GameRole = GameRole(Cow::Borrowed("Jester"));
+
+    fn tournament(players: usize, seed: u64) -> TournamentConfig {
+        TournamentConfig {
+            api_base: String::new(),
+            api_key: None,
+            models: (0..players)
+                .map(|index| TournamentModel {
+                    model_id: format!("model-{}", index),
+                    instructions: None,
+                    reasoning_effort: None,
+                })
+                .collect(),
+            players,
+            seed,
+            start_at_night: false,
+            setup: Some(builtin_presets()[1].clone()),
+        }
+    }
+
+    #[test]
+    fn games_deal_the_seat_roles() {
+        for seed in 0..20 {
+            let config = tournament(8, seed);
+            let roles = config.seat_roles();
+            for (lobby, game_seed) in config.schedule(1) {
+                let mut game = Game::new(Box::new(NullFrontend), None, game_seed);
+                game.state.roles = lobby.role_book();
+                game.setup = lobby.setup.clone().unwrap();
+                game.init_actors(lobby.build_actors());
+                let dealt = game
+                    .state
+                    .actors
+                    .iter()
+                    .map(|actor| actor.role.clone())
+                    .collect::<Vec<_>>();
+                assert_eq!(dealt, roles, "seed {}", seed);
+            }
+        }
+    }
+
+    /// A finished game, one seat per `(model, role, alignment, won, survived)`
+    fn game(seats: &[(&str, GameRole, RoleAlignment, bool, bool)]) -> GameSummary {
+        GameSummary {
+            index: 0,
+            seed: 0,
+            game_id: None,
+            result: Some(EndResult {
+                faction: Some(RoleAlignment::Town),
+                personal_wins: Vec::new(),
+            }),
+            days: 1,
+            nights: 1,
+            actors: seats
+                .iter()
+                .enumerate()
+                .map(
+                    |(id, (model_id, role, alignment, won, survived))| ActorSummary {
+                        id: id as u8,
+                        name: model_id.to_string(),
+                        model_id: Some(model_id.to_string()),
+                        role: role.clone(),
+                        alignment: alignment.clone(),
+                        survived: *survived,
+                        won: *won,
+                    },
+                )
+                .collect(),
+            error: None,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn wilson_interval_matches_known_bounds() {
+        let (low, high) = wilson_interval(5, 10);
+        assert_close(low, 0.2366);
+        assert_close(high, 0.7634);
+        let (low, high) = wilson_interval(0, 10);
+        assert_close(low, 0.0);
+        assert_close(high, 0.2775);
+        let (low, high) = wilson_interval(10, 10);
+        assert_close(low, 0.7225);
+        assert_close(high, 1.0);
+    }
+
+    #[test]
+    fn wilson_interval_without_games_is_everything() {
+        assert_eq!(wilson_interval(0, 0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn equal_ratings_exchange_symmetrically() {
+        let mut ratings = Ratings::default();
+        ratings.record(&game(&[
+            (
+                "mafia",
+                GameRole::MAFIOSO,
+                RoleAlignment::Mafia,
+                false,
+                false,
+            ),
+            ("town", GameRole::VILLAGER, RoleAlignment::Town, true, true),
+        ]));
+        assert_close(ratings.elo("town"), STARTING_ELO + K_FACTOR / 2.0);
+        assert_close(ratings.elo("mafia"), STARTING_ELO - K_FACTOR / 2.0);
+    }
+
+    #[test]
+    fn neutral_is_rated_on_their_own_win() {
+        let mut ratings = Ratings::default();
+        // The Jester won by dying and the Town lost, surviving doesn't count for the Jester
+        ratings.record(&game(&[
+            ("mafia", GameRole::MAFIOSO, RoleAlignment::Mafia, true, true),
+            ("town", GameRole::VILLAGER, RoleAlignment::Town, false, true),
+            ("jester", JESTER, RoleAlignment::Neutral, true, false),
+        ]));
+        assert_close(ratings.elo("jester"), STARTING_ELO + K_FACTOR / 2.0);
+        assert_close(ratings.elo("mafia"), STARTING_ELO + K_FACTOR / 2.0);
+        assert_close(ratings.elo("town"), STARTING_ELO - K_FACTOR / 4.0);
+    }
+}